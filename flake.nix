@@ -102,6 +102,14 @@
           extraBinPath = [ pkgs.exiftool ];
         };
 
+        apod-wallpaper-headless = mkPackage {
+          pname = "apod-wallpaper-headless";
+          bin = "apod-wallpaper";
+          description = "Download-only build of apod-wallpaper for headless machines";
+          features = [ "download-only" ];
+          extraBinPath = [ pkgs.exiftool ];
+        };
+
         apod-wallpaper-switcher = mkPackage {
           pname = "apod-wallpaper-switcher";
           bin = "apod-wallpaper-switcher";
@@ -121,6 +129,14 @@
           ];
         };
 
+        apod-wallpaper-tui = mkPackage {
+          pname = "apod-wallpaper-tui";
+          bin = "apod-wallpaper-tui";
+          description = "Terminal wallpaper browser for apod";
+          features = [ "tui" ];
+          extraBinPath = [ pkgs.exiftool ];
+        };
+
         apod-wallpaper-all = pkgs.buildEnv {
           name = "apod-wallpaper-all";
           paths = [
@@ -133,8 +149,10 @@
         packages = {
           default = apod-wallpaper-all;
           apod-wallpaper = apod-wallpaper;
+          apod-wallpaper-headless = apod-wallpaper-headless;
           apod-wallpaper-switcher = apod-wallpaper-switcher;
           apod-wallpaper-applet = apod-wallpaper-applet;
+          apod-wallpaper-tui = apod-wallpaper-tui;
           apod-wallpaper-all = apod-wallpaper-all;
         };
 
@@ -1,36 +1,541 @@
-use crate::utils::command_exists;
+pub use crate::config::DEFAULT_MAX_RANGE_DAYS;
+use crate::config::FolderStructure;
+use crate::source::{
+    ExplanationProvider, FetchFuture, FetchedImage, ImageMetadata, ImageSource,
+    explanation_provider,
+};
+use crate::utils::{
+    acquire_lock, collect_files, command_exists, create_save_folder, date_from_file_name,
+    get_cache_dir, get_config_dir, is_image_file, list_image_files,
+};
 use crate::{Error, Result};
-use chrono::{Local, NaiveDate, Timelike, Utc};
+use chrono::{Datelike, Local, NaiveDate, Timelike, Utc, Weekday};
+use futures_util::{StreamExt, TryStreamExt};
 use rand::seq::IndexedRandom;
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
-#[derive(Debug, Deserialize)]
-struct ApodResponse {
-    date: String,
-    explanation: String,
+const PARTIAL_SUFFIX: &str = ".part";
+const RATE_LIMIT_WARNING_THRESHOLD: u64 = 5;
+// Dates downloaded at once, also the number of API requests allowed in flight
+const MAX_CONCURRENT_DOWNLOADS: usize = 4;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApodResponse {
+    pub date: String,
+    pub explanation: String,
     #[serde(rename = "hdurl")]
-    hd_url: Option<String>,
-    url: Option<String>,
-    title: String,
-    media_type: String,
+    pub hd_url: Option<String>,
+    pub url: Option<String>,
+    pub title: String,
+    pub copyright: Option<String>,
+    pub media_type: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DateFilter {
+    pub skip_weekdays: Vec<Weekday>,
+    pub skip_dates: Vec<NaiveDate>,
+}
+
+impl DateFilter {
+    pub fn skips(&self, date: NaiveDate) -> bool {
+        self.skip_weekdays.contains(&date.weekday()) || self.skip_dates.contains(&date)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum DownloadStatus {
+    Started,
+    Downloaded,
+    Skipped,
+    NoImage,
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    pub date: NaiveDate,
+    pub completed: usize,
+    pub total: usize,
+    pub status: DownloadStatus,
+}
+
+impl DownloadProgress {
+    pub fn print(&self) {
+        let date = self.date.format("%Y-%m-%d");
+        match &self.status {
+            DownloadStatus::Started => println!("Downloading APOD for {}...", date),
+            DownloadStatus::Downloaded => println!("Successfully downloaded image for {}", date),
+            DownloadStatus::Skipped => println!("Image for {} already exists, skipping", date),
+            DownloadStatus::NoImage => {
+                println!("No image available for {} (might be video content)", date)
+            }
+            DownloadStatus::Failed(e) => {
+                eprintln!("Failed to download image for {}: {}", date, e)
+            }
+        }
+    }
+}
+
+fn is_supported_extension(image_ext: &str) -> bool {
+    matches!(image_ext, "jpg" | "jpeg" | "png" | "tif" | "tiff" | "gif")
+}
+
+// Why a URL can never point to a still raster image, URLs without an extension are tried
+fn url_skip_reason(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let image_ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("jpg")
+        .to_lowercase();
+
+    if image_ext == "svg" {
+        Some("SVG is not a still raster image".to_string())
+    } else if !is_supported_extension(&image_ext) {
+        Some(format!("unsupported image format {}", image_ext))
+    } else {
+        None
+    }
+}
+
+// Prefer the APOD HD image, then fallback sources, then the SD copy; larger files win ties
+fn best_local_image(images: &[PathBuf], date: NaiveDate) -> Option<PathBuf> {
+    let date_ymd = date.format("%Y-%m-%d").to_string();
+    images
+        .iter()
+        .filter(|path| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            file_name.starts_with(&date_ymd) && is_image_file(path)
+        })
+        .min_by_key(|path| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let priority = match &stem[date_ymd.len().min(stem.len())..] {
+                "" => 0,
+                "-sd" => 2,
+                _ => 1,
+            };
+            let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            (priority, std::cmp::Reverse(size), (*path).clone())
+        })
+        .cloned()
+}
+
+fn raster_extension(image_bytes: &[u8]) -> Option<&'static str> {
+    match image::guess_format(image_bytes).ok()? {
+        image::ImageFormat::Jpeg => Some("jpg"),
+        image::ImageFormat::Png => Some("png"),
+        image::ImageFormat::Tiff => Some("tiff"),
+        image::ImageFormat::Gif => Some("gif"),
+        _ => None,
+    }
+}
+
+fn prepare_image(image_ext: &str, image_bytes: Vec<u8>) -> Result<(String, Vec<u8>)> {
+    if image_ext == "gif" {
+        // Only the first frame of animated GIFs is decoded
+        let img = image::load_from_memory_with_format(&image_bytes, image::ImageFormat::Gif)?;
+        let mut png_bytes = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut png_bytes, image::ImageFormat::Png)?;
+        return Ok(("png".to_string(), png_bytes.into_inner()));
+    }
+    if !matches!(image_ext, "tif" | "tiff") {
+        return Ok((image_ext.to_string(), image_bytes));
+    }
+
+    let img = image::load_from_memory_with_format(&image_bytes, image::ImageFormat::Tiff)?;
+    let mut jpeg_bytes = std::io::Cursor::new(Vec::new());
+    img.to_rgb8()
+        .write_to(&mut jpeg_bytes, image::ImageFormat::Jpeg)?;
+    Ok(("jpg".to_string(), jpeg_bytes.into_inner()))
+}
+
+// ETag and Last-Modified of downloaded images, keyed by URL
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CacheValidators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+fn validators_path() -> Result<PathBuf> {
+    Ok(get_cache_dir()?.join("image-validators.json"))
+}
+
+fn load_validators(url: &str) -> Option<CacheValidators> {
+    let content = fs::read_to_string(validators_path().ok()?).ok()?;
+    let mut validators: HashMap<String, CacheValidators> = serde_json::from_str(&content).ok()?;
+    validators.remove(url)
+}
+
+fn store_validators(url: &str, validators: CacheValidators) -> Result<()> {
+    let _lock = acquire_lock("validators")?;
+    let path = validators_path()?;
+    let mut all: HashMap<String, CacheValidators> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    if validators.etag.is_none() && validators.last_modified.is_none() {
+        all.remove(url);
+    } else {
+        all.insert(url.to_string(), validators);
+    }
+    let content = serde_json::to_string(&all).map_err(|e| Error::Config(e.to_string()))?;
+    fs::write(&path, content)?;
+    Ok(())
+}
+
+struct StreamedFile {
+    content_type: String,
+    validators: CacheValidators,
+}
+
+fn write_image_file(file_path: &Path, image_bytes: &[u8]) -> Result<()> {
+    let mut partial_path = file_path.as_os_str().to_owned();
+    partial_path.push(PARTIAL_SUFFIX);
+    let partial_path = PathBuf::from(partial_path);
+
+    fs::write(&partial_path, image_bytes)
+        .and_then(|_| fs::rename(&partial_path, file_path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&partial_path);
+            Error::Config(format!(
+                "Could not write image to {}: {}",
+                file_path.display(),
+                e
+            ))
+        })
+}
+
+const DEFAULT_API_BASE: &str = "https://api.nasa.gov/planetary/apod";
+
+fn apod_start_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1995, 6, 16).unwrap()
+}
+
+// One YYYY-MM-DD date per line, invalid lines are reported and skipped
+pub fn read_date_list(path: &Path) -> Result<Vec<NaiveDate>> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        Error::Config(format!(
+            "Could not read dates from {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let mut dates = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let date = NaiveDate::parse_from_str(line, "%Y-%m-%d")
+            .map_err(|_| Error::Api(format!("Invalid date format: {}. Use YYYY-MM-DD", line)))
+            .and_then(|date| check_apod_date(date).map(|_| date));
+        match date {
+            Ok(date) if !dates.contains(&date) => dates.push(date),
+            Ok(_) => {}
+            Err(e) => eprintln!("Skipping line {} of {}: {}", number + 1, path.display(), e),
+        }
+    }
+    Ok(dates)
+}
+
+fn check_apod_date(date: NaiveDate) -> Result<()> {
+    let today = Local::now().date_naive();
+    if date < apod_start_date() || date > today {
+        return Err(Error::Api(format!(
+            "There is no APOD for {}, dates must be between {} and {}",
+            date,
+            apod_start_date(),
+            today
+        )));
+    }
+    Ok(())
+}
+
+pub fn api_key_from_env() -> Option<String> {
+    if let Ok(key_file) = std::env::var("NASA_API_KEY_FILE") {
+        match fs::read_to_string(&key_file) {
+            Ok(key) if !key.trim().is_empty() => return Some(key.trim().to_string()),
+            Ok(_) => eprintln!("Warning: NASA_API_KEY_FILE {} is empty", key_file),
+            Err(e) => eprintln!(
+                "Warning: Could not read NASA_API_KEY_FILE {}: {}",
+                key_file, e
+            ),
+        }
+    }
+    std::env::var("NASA_API_KEY").ok()
+}
+
+fn api_key_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join("api_key"))
+}
+
+// Written by config set-key, used when neither --api-key nor the environment provide a key
+pub fn stored_api_key() -> Option<String> {
+    let key = fs::read_to_string(api_key_path().ok()?).ok()?;
+    let key = key.trim();
+    (!key.is_empty()).then(|| key.to_string())
+}
+
+// Only readable by the current user, unlike the config file
+pub fn store_api_key(key: &str) -> Result<PathBuf> {
+    let path = api_key_path()?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        if path.exists() {
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(&path)?.write_all(key.trim().as_bytes())?;
+    Ok(path)
+}
+
+struct ExifTool {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl ExifTool {
+    fn spawn() -> std::io::Result<Self> {
+        let mut child = Command::new("exiftool")
+            .args(["-stay_open", "True", "-@", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            return Err(std::io::Error::other("exiftool pipes unavailable"));
+        };
+
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    fn execute(&mut self, args: &[String]) -> Result<String> {
+        for arg in args {
+            writeln!(self.stdin, "{}", arg)?;
+        }
+        writeln!(self.stdin, "-execute")?;
+        self.stdin.flush()?;
+
+        let mut output = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line)? == 0 {
+                return Err(Error::DesktopEnv(
+                    "exiftool exited unexpectedly".to_string(),
+                ));
+            }
+            if line.trim() == "{ready}" {
+                return Ok(output);
+            }
+            output.push_str(&line);
+        }
+    }
+}
+
+impl Drop for ExifTool {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "-stay_open\nFalse");
+        let _ = self.stdin.flush();
+        let _ = self.child.wait();
+    }
 }
 
 pub struct ApodClient {
     client: Client,
     api_key: Option<String>,
+    api_base: String,
+    keep_both: bool,
+    keep_gif: bool,
+    prefer_hd: bool,
+    force: bool,
+    folder_structure: FolderStructure,
+    skip_metadata: bool,
+    max_range_days: Option<usize>,
+    blacklist: Vec<NaiveDate>,
+    explanation_provider: Option<Box<dyn ExplanationProvider>>,
+    exiftool: Mutex<Option<ExifTool>>,
+    // Listed once per folder, a walk-back would otherwise rescan the archive for every day
+    local_images: Mutex<Option<(PathBuf, Vec<PathBuf>)>>,
+    downloaded_bytes: AtomicU64,
+    rate_limit_warned: AtomicBool,
+    // Shared by every API request so concurrent downloads stay within the rate limit
+    api_permits: tokio::sync::Semaphore,
 }
 
-impl ApodClient {
-    pub fn new() -> Self {
+impl Default for ApodClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Defaults match ApodClient::new(), unset options fall back to the environment
+#[derive(Debug, Clone)]
+pub struct ApodClientBuilder {
+    api_key: Option<String>,
+    api_base: Option<String>,
+    keep_both: bool,
+    keep_gif: bool,
+    prefer_hd: bool,
+    force: bool,
+    folder_structure: FolderStructure,
+    skip_metadata: bool,
+    max_range_days: Option<usize>,
+    blacklist: Vec<NaiveDate>,
+    explanation_language: Option<String>,
+    explanation_mirror: Option<String>,
+}
+
+impl Default for ApodClientBuilder {
+    fn default() -> Self {
         Self {
+            api_key: None,
+            api_base: None,
+            keep_both: false,
+            keep_gif: false,
+            prefer_hd: true,
+            force: false,
+            folder_structure: FolderStructure::Flat,
+            skip_metadata: false,
+            max_range_days: Some(DEFAULT_MAX_RANGE_DAYS),
+            blacklist: Vec::new(),
+            explanation_language: None,
+            explanation_mirror: None,
+        }
+    }
+}
+
+impl ApodClientBuilder {
+    // Overrides NASA_API_KEY_FILE, NASA_API_KEY and the key stored by config set-key
+    pub fn api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key.or(self.api_key);
+        self
+    }
+
+    // Overrides APOD_API_BASE, e.g. for a mirror or a local mock server
+    pub fn api_base(mut self, api_base: Option<String>) -> Self {
+        self.api_base = api_base.or(self.api_base);
+        self
+    }
+
+    pub fn keep_both(mut self, keep_both: bool) -> Self {
+        self.keep_both = keep_both;
+        self
+    }
+
+    // Animated GIFs are always set as their first frame, this also keeps the original
+    pub fn keep_gif(mut self, keep_gif: bool) -> Self {
+        self.keep_gif = keep_gif;
+        self
+    }
+
+    pub fn prefer_hd(mut self, prefer_hd: bool) -> Self {
+        self.prefer_hd = prefer_hd;
+        self
+    }
+
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    pub fn folder_structure(mut self, folder_structure: FolderStructure) -> Self {
+        self.folder_structure = folder_structure;
+        self
+    }
+
+    pub fn skip_metadata(mut self, skip_metadata: bool) -> Self {
+        self.skip_metadata = skip_metadata;
+        self
+    }
+
+    // Larger date ranges are refused, None allows any size
+    pub fn max_range_days(mut self, max_range_days: Option<usize>) -> Self {
+        self.max_range_days = max_range_days;
+        self
+    }
+
+    // Blacklisted dates are skipped when picking an image, explicit downloads still fetch them
+    pub fn blacklist(mut self, blacklist: Vec<NaiveDate>) -> Self {
+        self.blacklist = blacklist;
+        self
+    }
+
+    // Stored in the image metadata instead of the English explanation when available
+    pub fn explanation_language(mut self, language: Option<String>) -> Self {
+        self.explanation_language = language;
+        self
+    }
+
+    pub fn explanation_mirror(mut self, mirror: Option<String>) -> Self {
+        self.explanation_mirror = mirror;
+        self
+    }
+
+    pub fn build(self) -> ApodClient {
+        ApodClient {
             client: Client::new(),
-            api_key: std::env::var("NASA_API_KEY").ok(),
+            api_key: self
+                .api_key
+                .or_else(api_key_from_env)
+                .or_else(stored_api_key),
+            api_base: self
+                .api_base
+                .or_else(|| std::env::var("APOD_API_BASE").ok())
+                .unwrap_or_else(|| DEFAULT_API_BASE.to_string()),
+            keep_both: self.keep_both,
+            keep_gif: self.keep_gif,
+            prefer_hd: self.prefer_hd,
+            force: self.force,
+            folder_structure: self.folder_structure,
+            skip_metadata: self.skip_metadata,
+            max_range_days: self.max_range_days,
+            blacklist: self.blacklist,
+            explanation_provider: self.explanation_language.as_deref().and_then(|language| {
+                explanation_provider(language, self.explanation_mirror.as_deref())
+            }),
+            exiftool: Mutex::new(None),
+            local_images: Mutex::new(None),
+            downloaded_bytes: AtomicU64::new(0),
+            rate_limit_warned: AtomicBool::new(false),
+            api_permits: tokio::sync::Semaphore::new(MAX_CONCURRENT_DOWNLOADS),
         }
     }
+}
+
+impl ApodClient {
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> ApodClientBuilder {
+        ApodClientBuilder::default()
+    }
+
+    pub fn downloaded_bytes(&self) -> u64 {
+        self.downloaded_bytes.load(Ordering::Relaxed)
+    }
 
     pub async fn get_image(
         &self,
@@ -38,44 +543,89 @@ impl ApodClient {
         random: bool,
         date_offset: Option<usize>,
     ) -> Result<Option<PathBuf>> {
-        if !folder.exists() {
-            fs::create_dir_all(folder)?;
+        create_save_folder(folder)?;
+
+        if let Some(image_path) = self.get_local_image(folder, random, date_offset)? {
+            return Ok(Some(image_path));
         }
 
-        let target_date = if random {
-            None
-        } else {
-            let today = Utc::now().naive_utc().date();
-            let mut offset = date_offset.unwrap_or(0) as i64;
-            if Utc::now().hour() < 5 {
-                offset += 1;
-            }
-            Some(today - chrono::Duration::days(offset))
-        };
+        let target_date = Self::target_date(random, date_offset);
+        if target_date.is_some_and(|date| self.is_blacklisted(date)) {
+            return Ok(None);
+        }
+        self.download_single_image(folder, target_date, random)
+            .await
+    }
 
-        if random {
-            if let Some(image_path) = self.get_random_local_image(folder)? {
-                return Ok(Some(image_path));
-            }
-        } else if let Some(date) = target_date {
-            if let Some(image_path) = self.get_local_image_for_date(folder, date)? {
-                return Ok(Some(image_path));
-            }
+    pub fn get_local_image(
+        &self,
+        folder: &Path,
+        random: bool,
+        date_offset: Option<usize>,
+    ) -> Result<Option<PathBuf>> {
+        match Self::target_date(random, date_offset) {
+            Some(date) if self.is_blacklisted(date) => Ok(None),
+            Some(date) => self.get_local_image_for_date(folder, date),
+            None => self.get_random_local_image(folder),
         }
+    }
 
-        if let Some(date) = target_date {
-            self.download_single_image(folder, Some(date), random).await
-        } else {
-            self.download_single_image(folder, None, random).await
+    async fn localized_explanation(&self, date: NaiveDate, english: &str) -> String {
+        let Some(provider) = self
+            .explanation_provider
+            .as_ref()
+            .filter(|_| !self.skip_metadata)
+        else {
+            return english.to_string();
+        };
+        match provider.fetch(date).await {
+            Ok(Some(explanation)) => explanation,
+            Ok(None) => english.to_string(),
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to fetch the {} explanation for {}: {}, using English",
+                    provider.language(),
+                    date,
+                    e
+                );
+                english.to_string()
+            }
         }
     }
 
-    pub async fn download_range(&self, folder: &Path, days: usize) -> Result<usize> {
-        if !folder.exists() {
-            fs::create_dir_all(folder)?;
+    pub fn is_blacklisted(&self, date: NaiveDate) -> bool {
+        self.blacklist.contains(&date)
+    }
+
+    pub fn get_latest_local_image(&self, folder: &Path) -> Result<Option<PathBuf>> {
+        let images = list_image_files(folder)?;
+        let latest = images
+            .iter()
+            .filter_map(|path| date_from_file_name(path))
+            .filter(|date| !self.is_blacklisted(*date))
+            .max();
+        Ok(latest.and_then(|date| best_local_image(&images, date)))
+    }
+
+    pub fn target_date(random: bool, date_offset: Option<usize>) -> Option<NaiveDate> {
+        if random {
+            return None;
         }
 
-        let mut downloaded_count = 0;
+        let today = Utc::now().naive_utc().date();
+        let mut offset = date_offset.unwrap_or(0) as i64;
+        if Utc::now().hour() < 5 {
+            offset += 1;
+        }
+        Some(today - chrono::Duration::days(offset))
+    }
+
+    pub async fn download_range(
+        &self,
+        folder: &Path,
+        days: usize,
+        on_progress: impl FnMut(&DownloadProgress),
+    ) -> Result<usize> {
         let today = Local::now().naive_local().date();
         let start = if Utc::now().hour() < 5 {
             today - chrono::Duration::days(1)
@@ -83,157 +633,556 @@ impl ApodClient {
             today
         };
 
-        for day_offset in 0..days {
-            let target_date = start - chrono::Duration::days(day_offset as i64);
+        let dates: Vec<NaiveDate> = (0..days)
+            .map(|day_offset| start - chrono::Duration::days(day_offset as i64))
+            .collect();
 
-            if self
-                .get_local_image_for_date(folder, target_date)?
-                .is_some()
-            {
-                println!("Image for {} already exists, skipping", target_date);
-                continue;
-            }
+        self.download_dates(folder, &dates, on_progress).await
+    }
 
-            println!("Downloading APOD for {}...", target_date.format("%Y-%m-%d"));
+    pub async fn download_dates(
+        &self,
+        folder: &Path,
+        dates: &[NaiveDate],
+        mut on_progress: impl FnMut(&DownloadProgress),
+    ) -> Result<usize> {
+        create_save_folder(folder)?;
 
-            match self
-                .download_single_image(folder, Some(target_date), false)
-                .await
-            {
-                Ok(Some(_)) => {
-                    downloaded_count += 1;
-                    println!(
-                        "Successfully downloaded image for {}",
-                        target_date.format("%Y-%m-%d")
-                    );
-                }
-                Ok(None) => {
-                    println!(
-                        "No image available for {} (might be video content)",
-                        target_date.format("%Y-%m-%d")
-                    );
-                }
-                Err(e) => {
-                    eprintln!(
-                        "Failed to download image for {}: {}",
-                        target_date.format("%Y-%m-%d"),
-                        e
-                    );
-                }
+        let mut downloaded_count = 0;
+        let mut completed = 0;
+        let total = dates.len();
+        let mut report = |date, completed, status| {
+            on_progress(&DownloadProgress {
+                date,
+                completed,
+                total,
+                status,
+            })
+        };
+
+        let mut missing = Vec::new();
+        for &date in dates {
+            if !self.force && self.get_local_image_for_date(folder, date)?.is_some() {
+                completed += 1;
+                report(date, completed, DownloadStatus::Skipped);
+            } else {
+                report(date, completed, DownloadStatus::Started);
+                missing.push(date);
+            }
+        }
+
+        // Results arrive in completion order, not in the order of the dates
+        let mut downloads = futures_util::stream::iter(missing)
+            .map(|date| async move {
+                let status = match self.download_single_image(folder, Some(date), false).await {
+                    Ok(Some(_)) => DownloadStatus::Downloaded,
+                    Ok(None) => DownloadStatus::NoImage,
+                    Err(e) => DownloadStatus::Failed(e.to_string()),
+                };
+                (date, status)
+            })
+            .buffer_unordered(MAX_CONCURRENT_DOWNLOADS);
+        while let Some((date, status)) = downloads.next().await {
+            if matches!(status, DownloadStatus::Downloaded) {
+                downloaded_count += 1;
             }
+            completed += 1;
+            report(date, completed, status);
         }
 
         Ok(downloaded_count)
     }
 
-    async fn download_single_image(
+    pub async fn download_matching(
         &self,
         folder: &Path,
-        target_date: Option<NaiveDate>,
-        random: bool,
-    ) -> Result<Option<PathBuf>> {
-        let api_key = self.api_key.as_deref().unwrap_or("DEMO_KEY");
-        let mut url = format!("https://api.nasa.gov/planetary/apod?api_key={}", api_key);
+        days: usize,
+        keywords: &[String],
+        mut on_progress: impl FnMut(&DownloadProgress),
+    ) -> Result<usize> {
+        create_save_folder(folder)?;
 
-        if random {
-            url.push_str("&count=1");
-        } else if let Some(date) = target_date {
-            let formatted_date = date.format("%Y-%m-%d").to_string();
-            url.push_str(&format!("&date={}", formatted_date));
+        let end = Self::target_date(false, None).unwrap_or_else(|| Utc::now().date_naive());
+        let start = end - chrono::Duration::days(days.saturating_sub(1) as i64);
+        let keywords: Vec<String> = keywords.iter().map(|k| k.to_lowercase()).collect();
+
+        let matching: Vec<ApodResponse> = self
+            .fetch_apod_range(start, end)
+            .await?
+            .into_iter()
+            .filter(|apod| {
+                let text = format!("{} {}", apod.title, apod.explanation).to_lowercase();
+                keywords.iter().any(|keyword| text.contains(keyword))
+            })
+            .collect();
+
+        let mut downloaded_count = 0;
+        let total = matching.len();
+
+        for (completed, apod) in matching.iter().enumerate() {
+            let Ok(date) = NaiveDate::parse_from_str(&apod.date, "%Y-%m-%d") else {
+                continue;
+            };
+            let mut report = |completed, status| {
+                on_progress(&DownloadProgress {
+                    date,
+                    completed,
+                    total,
+                    status,
+                })
+            };
+
+            if !self.force && self.get_local_image_for_date(folder, date)?.is_some() {
+                report(completed + 1, DownloadStatus::Skipped);
+                continue;
+            }
+
+            report(completed, DownloadStatus::Started);
+
+            let status = if apod.media_type != "image" {
+                DownloadStatus::NoImage
+            } else {
+                match self.save_apod_image(folder, apod).await {
+                    Ok(Some(_)) => {
+                        downloaded_count += 1;
+                        DownloadStatus::Downloaded
+                    }
+                    Ok(None) => DownloadStatus::NoImage,
+                    Err(e) => DownloadStatus::Failed(e.to_string()),
+                }
+            };
+            report(completed + 1, status);
         }
 
-        let response = self.client.get(&url).send().await?;
+        Ok(downloaded_count)
+    }
+
+    async fn send_apod_request(&self, query: &str) -> Result<Option<reqwest::Response>> {
+        let api_key = self.api_key.as_deref().unwrap_or("DEMO_KEY");
+        let url = format!(
+            "{}?api_key={}{}",
+            self.api_base.trim_end_matches('/'),
+            api_key,
+            query
+        );
+
+        let response = {
+            let _permit = self
+                .api_permits
+                .acquire()
+                .await
+                .map_err(|e| Error::Api(e.to_string()))?;
+            self.client.get(&url).send().await?
+        };
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let key_hint = if self.api_key.is_none() {
+            " Run config set-key or set NASA_API_KEY to use your own key (https://api.nasa.gov) instead of DEMO_KEY."
+        } else {
+            ""
+        };
+
+        if let Some(remaining) = header("X-RateLimit-Remaining").and_then(|v| v.parse::<u64>().ok())
+            && remaining <= RATE_LIMIT_WARNING_THRESHOLD
+            && !self.rate_limit_warned.swap(true, Ordering::Relaxed)
+        {
+            eprintln!(
+                "Warning: Only {} NASA API requests remaining.{}",
+                remaining, key_hint
+            );
+        }
 
         if !response.status().is_success() {
             let status = response.status();
-            return if status.as_u16() == 403 {
-                Err(Error::Api(
-                    "API rate limit exceeded or invalid API key".to_string(),
-                ))
+            return if matches!(status.as_u16(), 403 | 429) {
+                let reset = header("X-RateLimit-Reset")
+                    .or_else(|| header("Retry-After"))
+                    .map(|reset| format!(" (resets in {} seconds)", reset))
+                    .unwrap_or_default();
+                Err(Error::Api(format!(
+                    "API rate limit exceeded or invalid API key{}.{}",
+                    reset, key_hint
+                )))
             } else if status.as_u16() == 404 {
                 Ok(None)
             } else {
+                // Errors like out of range dates come with a JSON body explaining the problem
+                let message = response
+                    .json::<serde_json::Value>()
+                    .await
+                    .ok()
+                    .and_then(|body| body.get("msg")?.as_str().map(str::to_string))
+                    .map(|msg| format!(": {}", msg))
+                    .unwrap_or_default();
                 Err(Error::Api(format!(
-                    "Failed to fetch APOD data: HTTP {}",
-                    response.status()
+                    "Failed to fetch APOD data: HTTP {}{}",
+                    status, message
                 )))
             };
         }
 
+        Ok(Some(response))
+    }
+
+    // Today's entry when no date is given, including videos and other non-image media
+    pub async fn fetch_metadata(&self, date: Option<NaiveDate>) -> Result<Option<ApodResponse>> {
+        self.fetch_apod_entry(date, false).await
+    }
+
+    async fn fetch_apod_data(
+        &self,
+        target_date: Option<NaiveDate>,
+        random: bool,
+    ) -> Result<Option<ApodResponse>> {
+        Ok(self
+            .fetch_apod_entry(target_date, random)
+            .await?
+            .filter(|apod| apod.media_type == "image"))
+    }
+
+    async fn fetch_apod_entry(
+        &self,
+        target_date: Option<NaiveDate>,
+        random: bool,
+    ) -> Result<Option<ApodResponse>> {
+        let mut query = String::new();
+        if random {
+            query.push_str("&count=1");
+        } else if let Some(date) = target_date {
+            let formatted_date = date.format("%Y-%m-%d").to_string();
+            query.push_str(&format!("&date={}", formatted_date));
+        }
+
+        let Some(response) = self.send_apod_request(&query).await? else {
+            return Ok(None);
+        };
+
         let apod_data: Vec<ApodResponse> = if random {
             response.json().await?
         } else {
             vec![response.json().await?]
         };
 
-        if apod_data.is_empty() || apod_data[0].media_type != "image" {
+        Ok(apod_data.into_iter().next())
+    }
+
+    async fn fetch_apod_range(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<ApodResponse>> {
+        let query = format!(
+            "&start_date={}&end_date={}",
+            start.format("%Y-%m-%d"),
+            end.format("%Y-%m-%d")
+        );
+
+        match self.send_apod_request(&query).await? {
+            Some(response) => Ok(response.json().await?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn download_single_image(
+        &self,
+        folder: &Path,
+        target_date: Option<NaiveDate>,
+        random: bool,
+    ) -> Result<Option<PathBuf>> {
+        let Some(apod) = self.fetch_apod_data(target_date, random).await? else {
+            return Ok(None);
+        };
+        // Random entries are only known after the request
+        if NaiveDate::parse_from_str(&apod.date, "%Y-%m-%d")
+            .is_ok_and(|date| self.is_blacklisted(date))
+        {
+            return Ok(None);
+        }
+
+        self.save_apod_image(folder, &apod).await
+    }
+
+    async fn save_apod_image(&self, folder: &Path, apod: &ApodResponse) -> Result<Option<PathBuf>> {
+        // Keeping both versions always stores the HD image under the plain date
+        let (preferred_url, other_url) = if self.prefer_hd || self.keep_both {
+            (&apod.hd_url, &apod.url)
+        } else {
+            (&apod.url, &apod.hd_url)
+        };
+        let Some(image_url) = preferred_url.as_ref().or(other_url.as_ref()) else {
+            eprintln!(
+                "Skipping APOD for {}: no image URL in the response",
+                apod.date
+            );
             return Ok(None);
+        };
+
+        let mut variants = vec![(image_url, apod.date.clone())];
+        if self.keep_both
+            && let (Some(_), Some(sd_url)) = (&apod.hd_url, &apod.url)
+            && sd_url != image_url
+        {
+            variants.push((sd_url, format!("{}-sd", apod.date)));
         }
 
-        let apod = &apod_data[0];
-        let image_url = apod.hd_url.as_ref().unwrap_or(apod.url.as_ref().unwrap());
-        let image_ext =
-            if let Some(ext) = Path::new(image_url).extension().and_then(|e| e.to_str()) {
-                ext
+        let folder = &folder.join(self.folder_structure.subfolder(&apod.date));
+        create_save_folder(folder)?;
+
+        let date = NaiveDate::parse_from_str(&apod.date, "%Y-%m-%d")
+            .map_err(|_| Error::Api(format!("Invalid date in APOD response: {}", apod.date)))?;
+        let metadata = ImageMetadata {
+            date,
+            title: apod.title.clone(),
+            explanation: self.localized_explanation(date, &apod.explanation).await,
+            copyright: apod.copyright.clone(),
+        };
+
+        let mut saved_path = None;
+        for (variant_url, file_stem) in variants {
+            let result = self
+                .fetch_image_to(variant_url, folder, &file_stem, &metadata)
+                .await;
+
+            match result {
+                Ok(Some(file_path)) if saved_path.is_none() => saved_path = Some(file_path),
+                Ok(_) => {}
+                Err(e) if saved_path.is_none() => return Err(e),
+                Err(e) => eprintln!("Warning: Failed to save standard version: {}", e),
+            }
+        }
+
+        Ok(saved_path)
+    }
+
+    // Returns None unless the URL points to a JPEG, PNG, TIFF or GIF image, GIFs become PNGs
+    pub async fn fetch_image_to(
+        &self,
+        url: &str,
+        folder: &Path,
+        file_stem: &str,
+        metadata: &ImageMetadata,
+    ) -> Result<Option<PathBuf>> {
+        if let Some(reason) = url_skip_reason(url) {
+            eprintln!("Skipping {}: {}", url, reason);
+            return Ok(None);
+        }
+
+        // An image we already have is only downloaded again when the server says it changed
+        let existing = ["jpg", "png"]
+            .iter()
+            .map(|ext| folder.join(format!("{}.{}", file_stem, ext)))
+            .find(|path| path.exists());
+        let validators = existing.as_ref().and_then(|_| load_validators(url));
+
+        let partial_path = folder.join(format!("{}{}", file_stem, PARTIAL_SUFFIX));
+        let Some(streamed) = self
+            .stream_to_file(url, &partial_path, validators.as_ref())
+            .await?
+        else {
+            return Ok(existing);
+        };
+        let content_type = streamed.content_type;
+
+        // Interactive APODs sometimes link an HTML page or embed instead of an image
+        let mut head = [0u8; 16];
+        let head_len = fs::File::open(&partial_path)?.read(&mut head)?;
+        let Some(image_ext) = raster_extension(&head[..head_len]) else {
+            let _ = fs::remove_file(&partial_path);
+            eprintln!(
+                "Skipping {}: not a JPEG, PNG, TIFF or GIF image (content type {})",
+                url, content_type
+            );
+            return Ok(None);
+        };
+
+        let file_path = if matches!(image_ext, "tiff" | "gif") {
+            let image_bytes = fs::read(&partial_path)?;
+            if image_ext == "gif" && self.keep_gif {
+                fs::rename(&partial_path, folder.join(format!("{}.gif", file_stem)))?;
             } else {
-                "jpg"
+                let _ = fs::remove_file(&partial_path);
+            }
+            let (image_ext, image_bytes) = prepare_image(image_ext, image_bytes)?;
+            let file_path = folder.join(format!("{}.{}", file_stem, image_ext));
+            write_image_file(&file_path, &image_bytes)?;
+            file_path
+        } else {
+            let file_path = folder.join(format!("{}.{}", file_stem, image_ext));
+            fs::rename(&partial_path, &file_path)?;
+            file_path
+        };
+        self.forget_local_images();
+
+        if let Err(e) = self.add_exif_metadata(&file_path, metadata) {
+            eprintln!("Warning: Failed to add EXIF metadata: {}", e);
+        }
+        if let Err(e) = store_validators(url, streamed.validators) {
+            eprintln!("Warning: Failed to store cache validators: {}", e);
+        }
+
+        Ok(Some(file_path))
+    }
+
+    pub async fn download_to_file(&self, url: &str, file_path: &Path) -> Result<()> {
+        let mut partial_path = file_path.as_os_str().to_owned();
+        partial_path.push(PARTIAL_SUFFIX);
+        let partial_path = PathBuf::from(partial_path);
+
+        self.stream_to_file(url, &partial_path, None).await?;
+        fs::rename(&partial_path, file_path)?;
+        self.forget_local_images();
+        Ok(())
+    }
+
+    // Returns None when the server answers a conditional request with 304 Not Modified
+    async fn stream_to_file(
+        &self,
+        url: &str,
+        file_path: &Path,
+        validators: Option<&CacheValidators>,
+    ) -> Result<Option<StreamedFile>> {
+        let mut request = self.client.get(url);
+        if let Some(validators) = validators {
+            if let Some(etag) = &validators.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
             }
-            .to_lowercase();
+        }
 
-        if image_ext != "jpg" && image_ext != "jpeg" && image_ext != "png" {
+        let response = request.send().await?;
+        if validators.is_some() && response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
             return Err(Error::Api(format!(
-                "Unsupported image format: {}",
-                image_ext
+                "Failed to download {}: HTTP {}",
+                url,
+                response.status()
             )));
         }
 
-        let file_name = format!("{}.{}", apod.date, image_ext);
-        let file_path = folder.join(file_name);
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let content_type =
+            header(reqwest::header::CONTENT_TYPE).unwrap_or_else(|| "unknown".to_string());
+        let validators = CacheValidators {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        };
 
-        let image_response = self.client.get(image_url).send().await?;
-        let image_bytes = image_response.bytes().await?;
+        let result = async {
+            let mut file = tokio::fs::File::create(file_path).await?;
+            let stream = response
+                .bytes_stream()
+                .inspect_ok(|chunk| {
+                    self.downloaded_bytes
+                        .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                })
+                .map_err(std::io::Error::other);
+            let mut reader = tokio_util::io::StreamReader::new(stream);
+            tokio::io::copy(&mut reader, &mut file).await?;
+            Ok::<(), Error>(())
+        }
+        .await;
 
-        fs::write(&file_path, image_bytes)?;
+        if let Err(e) = result {
+            let _ = fs::remove_file(file_path);
+            return Err(e);
+        }
+        Ok(Some(StreamedFile {
+            content_type,
+            validators,
+        }))
+    }
 
-        if let Err(e) = self.add_exif_metadata(&file_path, &apod.title, &apod.explanation) {
-            eprintln!("Warning: Failed to add EXIF metadata: {}", e);
+    pub async fn fetch_from_sources(
+        &self,
+        sources: &[Box<dyn ImageSource>],
+        folder: &Path,
+        date: NaiveDate,
+    ) -> Result<Option<PathBuf>> {
+        for source in sources {
+            let fetched = match source.fetch(date).await {
+                Ok(Some(fetched)) => fetched,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("Failed to fetch image from {}: {}", source.name(), e);
+                    continue;
+                }
+            };
+
+            let date = fetched.metadata.date.format("%Y-%m-%d").to_string();
+            let folder = folder.join(self.folder_structure.subfolder(&date));
+            create_save_folder(&folder)?;
+            let file_stem = format!("{}-{}", date, source.name());
+            // Skipped URLs are already reported by fetch_image_to
+            match self
+                .fetch_image_to(&fetched.url, &folder, &file_stem, &fetched.metadata)
+                .await
+            {
+                Ok(Some(file_path)) => return Ok(Some(file_path)),
+                Ok(None) => {}
+                Err(e) => eprintln!("Failed to download image from {}: {}", source.name(), e),
+            }
         }
 
-        Ok(Some(file_path))
+        Ok(None)
     }
 
-    pub async fn download_specific_date(&self, folder: &Path, date_str: &str) -> Result<usize> {
+    pub async fn download_specific_date(
+        &self,
+        folder: &Path,
+        date_str: &str,
+        mut on_progress: impl FnMut(&DownloadProgress),
+    ) -> Result<usize> {
         use chrono::NaiveDate;
 
         let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d").map_err(|_| {
             Error::Api(format!("Invalid date format: {}. Use YYYY-MM-DD", date_str))
         })?;
+        check_apod_date(date)?;
 
-        if !folder.exists() {
-            fs::create_dir_all(folder)?;
-        }
+        create_save_folder(folder)?;
 
-        if self.get_local_image_for_date(folder, date)?.is_some() {
-            println!("Image for {} already exists, skipping", date_str);
+        let mut report = |completed, status| {
+            on_progress(&DownloadProgress {
+                date,
+                completed,
+                total: 1,
+                status,
+            })
+        };
+
+        if !self.force && self.get_local_image_for_date(folder, date)?.is_some() {
+            report(1, DownloadStatus::Skipped);
             return Ok(0);
         }
 
-        println!("Downloading APOD for {}...", date_str);
+        report(0, DownloadStatus::Started);
 
         match self.download_single_image(folder, Some(date), false).await {
             Ok(Some(_)) => {
-                println!("Successfully downloaded image for {}", date_str);
+                report(1, DownloadStatus::Downloaded);
                 Ok(1)
             }
             Ok(None) => {
-                println!(
-                    "No image available for {} (might be video content)",
-                    date_str
-                );
+                report(1, DownloadStatus::NoImage);
                 Ok(0)
             }
             Err(e) => {
-                eprintln!("Failed to download image for {}: {}", date_str, e);
+                report(1, DownloadStatus::Failed(e.to_string()));
                 Err(e)
             }
         }
@@ -244,6 +1193,8 @@ impl ApodClient {
         folder: &Path,
         start_str: &str,
         end_str: &str,
+        filter: &DateFilter,
+        on_progress: impl FnMut(&DownloadProgress),
     ) -> Result<usize> {
         use chrono::NaiveDate;
 
@@ -266,65 +1217,36 @@ impl ApodClient {
                 "Start date must be before or equal to end date".to_string(),
             ));
         }
+        check_apod_date(start_date)?;
+        check_apod_date(end_date)?;
 
-        if !folder.exists() {
-            fs::create_dir_all(folder)?;
-        }
-
-        let mut downloaded_count = 0;
+        let mut dates = Vec::new();
         let mut current_date = start_date;
-
         while current_date <= end_date {
-            if self
-                .get_local_image_for_date(folder, current_date)?
-                .is_some()
-            {
-                println!(
-                    "Image for {} already exists, skipping",
-                    current_date.format("%Y-%m-%d")
-                );
-                current_date = current_date + chrono::Duration::days(1);
-                continue;
-            }
-
-            println!(
-                "Downloading APOD for {}...",
-                current_date.format("%Y-%m-%d")
-            );
-
-            match self
-                .download_single_image(folder, Some(current_date), false)
-                .await
-            {
-                Ok(Some(_)) => {
-                    downloaded_count += 1;
-                    println!(
-                        "Successfully downloaded image for {}",
-                        current_date.format("%Y-%m-%d")
-                    );
-                }
-                Ok(None) => {
-                    println!(
-                        "No image available for {} (might be video content)",
-                        current_date.format("%Y-%m-%d")
-                    );
-                }
-                Err(e) => {
-                    eprintln!(
-                        "Failed to download image for {}: {}",
-                        current_date.format("%Y-%m-%d"),
-                        e
-                    );
-                }
+            if !filter.skips(current_date) {
+                dates.push(current_date);
             }
+            current_date += chrono::Duration::days(1);
+        }
 
-            current_date = current_date + chrono::Duration::days(1);
+        if let Some(max_range_days) = self.max_range_days
+            && dates.len() > max_range_days
+        {
+            return Err(Error::Api(format!(
+                "The date range covers {} days, more than the limit of {}. Confirm with --yes or raise max_range_days in the config",
+                dates.len(),
+                max_range_days
+            )));
         }
 
-        Ok(downloaded_count)
+        self.download_dates(folder, &dates, on_progress).await
     }
 
-    fn add_exif_metadata(&self, file_path: &Path, title: &str, explanation: &str) -> Result<()> {
+    fn add_exif_metadata(&self, file_path: &Path, metadata: &ImageMetadata) -> Result<()> {
+        if self.skip_metadata {
+            return Ok(());
+        }
+
         if !command_exists("exiftool") {
             eprintln!(
                 "exiftool not found. EXIF metadata not added. Install exiftool for full metadata support."
@@ -332,56 +1254,97 @@ impl ApodClient {
             return Ok(());
         }
 
-        let file_path_str = file_path.to_string_lossy();
-        let title_arg = format!("-Title={}", title);
-        let description_arg = format!("-Description={}", explanation);
+        // Argument files are line based, so values have to stay on a single line
+        let mut args = vec![
+            "-overwrite_original".to_string(),
+            "-ifd0:all=".to_string(),
+            format!("-Title={}", metadata.title.replace('\n', " ")),
+            format!("-Description={}", metadata.explanation.replace('\n', " ")),
+            // Keeps the date available after the file has been renamed
+            format!(
+                "-DateTimeOriginal={} 00:00:00",
+                metadata.date.format("%Y:%m:%d")
+            ),
+        ];
+        if let Some(copyright) = &metadata.copyright {
+            args.push(format!(
+                "-Copyright={}",
+                copyright.trim().replace('\n', " ")
+            ));
+        }
+        args.push(file_path.to_string_lossy().to_string());
 
-        let result = Command::new("exiftool")
-            .arg("-overwrite_original")
-            .arg("-ifd0:all=")
-            .arg(&title_arg)
-            .arg(&description_arg)
-            .arg(file_path_str.as_ref())
-            .output();
+        let mut exiftool = self.exiftool.lock().unwrap_or_else(|e| e.into_inner());
+        if exiftool.is_none() {
+            *exiftool = ExifTool::spawn().ok();
+        }
+
+        let updated = match exiftool.as_mut() {
+            Some(process) => match process.execute(&args) {
+                Ok(output) => output.contains("1 image files updated"),
+                Err(e) => {
+                    *exiftool = None;
+                    return Err(e);
+                }
+            },
+            None => Command::new("exiftool")
+                .args(&args)
+                .output()
+                .map(|output| output.status.success())
+                .unwrap_or(false),
+        };
 
-        match result {
-            Ok(output) if output.status.success() => Ok(()),
-            Ok(_) => Err(Error::DesktopEnv(
+        if updated {
+            Ok(())
+        } else {
+            Err(Error::DesktopEnv(
                 "Failed to add EXIF metadata with exiftool".to_string(),
-            )),
-            Err(_) => {
-                eprintln!(
-                    "exiftool not found. EXIF metadata not added. Install exiftool for full metadata support."
-                );
-                Ok(())
-            }
+            ))
         }
     }
 
-    fn get_local_image_for_date(&self, folder: &Path, date: NaiveDate) -> Result<Option<PathBuf>> {
-        let date_ymd = date.format("%Y-%m-%d").to_string();
+    pub fn remove_partial_downloads(&self, folder: &Path) -> Result<usize> {
+        let mut removed = 0;
 
-        if let Ok(entries) = fs::read_dir(folder) {
-            for entry in entries.filter_map(|e| e.ok()) {
-                let file_name = entry.file_name().to_string_lossy().to_string();
-                if file_name.starts_with(&date_ymd) {
-                    return Ok(Some(entry.path()));
-                }
+        for path in collect_files(folder) {
+            if path.to_string_lossy().ends_with(PARTIAL_SUFFIX) {
+                fs::remove_file(path)?;
+                removed += 1;
             }
         }
 
-        Ok(None)
+        Ok(removed)
+    }
+
+    fn get_local_image_for_date(&self, folder: &Path, date: NaiveDate) -> Result<Option<PathBuf>> {
+        let mut local_images = self.local_images.lock().unwrap_or_else(|e| e.into_inner());
+        if local_images
+            .as_ref()
+            .is_none_or(|(listed, _)| listed != folder)
+        {
+            *local_images = Some((folder.to_path_buf(), list_image_files(folder)?));
+        }
+        let images = local_images.as_ref().map(|(_, images)| images.as_slice());
+        Ok(best_local_image(images.unwrap_or_default(), date))
+    }
+
+    // Called after every write so the next lookup lists the folder again
+    fn forget_local_images(&self) {
+        *self.local_images.lock().unwrap_or_else(|e| e.into_inner()) = None;
     }
 
     fn get_random_local_image(&self, folder: &Path) -> Result<Option<PathBuf>> {
         let mut images = Vec::new();
 
-        if let Ok(entries) = fs::read_dir(folder) {
-            for entry in entries.filter_map(|e| e.ok()) {
-                let path = entry.path();
-                if path.extension().map_or(false, |ext| ext == "jpg") {
-                    images.push(path);
-                }
+        for path in collect_files(folder) {
+            let is_jpg = path.extension().is_some_and(|ext| ext == "jpg");
+            // Same file name rule as WallpaperConfig::is_blacklisted, so the CLI and GUI agree
+            let blacklisted = || {
+                !self.blacklist.is_empty()
+                    && date_from_file_name(&path).is_some_and(|date| self.is_blacklisted(date))
+            };
+            if is_jpg && !blacklisted() {
+                images.push(path);
             }
         }
 
@@ -393,3 +1356,106 @@ impl ApodClient {
         Ok(None)
     }
 }
+
+impl ImageSource for ApodClient {
+    fn name(&self) -> &str {
+        "apod"
+    }
+
+    fn fetch(&self, date: NaiveDate) -> FetchFuture<'_> {
+        Box::pin(async move {
+            let Some(apod) = self.fetch_apod_data(Some(date), false).await? else {
+                return Ok(None);
+            };
+            let (preferred_url, other_url) = if self.prefer_hd {
+                (&apod.hd_url, &apod.url)
+            } else {
+                (&apod.url, &apod.hd_url)
+            };
+            let Some(image_url) = preferred_url.as_ref().or(other_url.as_ref()) else {
+                return Ok(None);
+            };
+
+            Ok(Some(FetchedImage {
+                url: image_url.clone(),
+                metadata: ImageMetadata {
+                    date,
+                    explanation: self.localized_explanation(date, &apod.explanation).await,
+                    title: apod.title,
+                    copyright: apod.copyright,
+                },
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn url_skip_reason_checks_the_extension() {
+        assert_eq!(
+            url_skip_reason("https://apod.nasa.gov/image/2401/a.jpg"),
+            None
+        );
+        assert_eq!(
+            url_skip_reason("https://apod.nasa.gov/image/2401/a.JPEG"),
+            None
+        );
+        assert_eq!(
+            url_skip_reason("https://apod.nasa.gov/image/2401/a.tif"),
+            None
+        );
+        assert_eq!(url_skip_reason("https://example.com/image"), None);
+        assert_eq!(
+            url_skip_reason("https://www.bing.com/th?id=OHR.Example_1920x1080.jpg&pid=hp"),
+            None
+        );
+        assert!(url_skip_reason("https://apod.nasa.gov/image/2401/a.svg").is_some());
+        assert!(url_skip_reason("https://apod.nasa.gov/image/2401/a.webp").is_some());
+    }
+
+    #[test]
+    fn raster_extension_follows_the_content() {
+        assert_eq!(raster_extension(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("jpg"));
+        assert_eq!(raster_extension(b"\x89PNG\r\n\x1a\n"), Some("png"));
+        assert_eq!(raster_extension(b"GIF89a"), Some("gif"));
+        assert_eq!(raster_extension(b"II*\0"), Some("tiff"));
+        // Interactive APODs serve an HTML page under an image URL
+        assert_eq!(raster_extension(b"<!DOCTYPE html>"), None);
+        assert_eq!(raster_extension(b""), None);
+    }
+
+    #[test]
+    fn best_local_image_prefers_hd() {
+        let images = [
+            PathBuf::from("apod/2024-01-01-sd.jpg"),
+            PathBuf::from("apod/2024-01-01.jpg"),
+            PathBuf::from("apod/2024-01-02.jpg"),
+        ];
+
+        assert_eq!(
+            best_local_image(&images, date("2024-01-01")),
+            Some(PathBuf::from("apod/2024-01-01.jpg"))
+        );
+    }
+
+    #[test]
+    fn best_local_image_ignores_kept_gifs() {
+        let images = [
+            PathBuf::from("apod/2024-01-01.gif"),
+            PathBuf::from("apod/2024-01-01.png"),
+        ];
+
+        assert_eq!(
+            best_local_image(&images, date("2024-01-01")),
+            Some(PathBuf::from("apod/2024-01-01.png"))
+        );
+        assert_eq!(best_local_image(&images[..1], date("2024-01-01")), None);
+    }
+}
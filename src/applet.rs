@@ -26,9 +26,23 @@ impl ApodWallpaperTray {
         }
     }
 
+    fn message_tooltip(description: String) -> ToolTip {
+        ToolTip {
+            title: "APOD Wallpaper".to_string(),
+            description,
+            icon_name: "".to_string(),
+            icon_pixmap: vec![],
+        }
+    }
+
     fn get_cached_tooltip(&self) -> ToolTip {
-        let manager = apod_wallpaper::desktop::get_wallpaper_manager().unwrap();
-        let current_wallpaper = manager.get_wallpaper(None).unwrap().unwrap();
+        let current_wallpaper = match apod_wallpaper::desktop::get_wallpaper_manager()
+            .and_then(|manager| manager.get_wallpaper(None))
+        {
+            Ok(Some(current_wallpaper)) => current_wallpaper,
+            Ok(None) => return Self::message_tooltip("No wallpaper set".to_string()),
+            Err(e) => return Self::message_tooltip(e.to_string()),
+        };
 
         let mut cache = self.cached_tooltip.lock().unwrap();
 
@@ -208,10 +222,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let tray = ApodWallpaperTray::new();
 
     println!("Creating tray service...");
-    tray.spawn().await.unwrap();
+    let handle = tray.spawn().await.unwrap();
 
-    std::future::pending::<()>().await;
+    apod_wallpaper::utils::shutdown_signal().await?;
 
     println!("Shutting down tray service...");
+    handle.shutdown().await;
     Ok(())
 }
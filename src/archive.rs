@@ -0,0 +1,151 @@
+use crate::utils::{date_from_file_name, date_from_metadata, get_all_metadata, list_image_files};
+use crate::{Error, Result};
+use serde::Serialize;
+use std::fs::write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Ndjson,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveEntry {
+    pub path: PathBuf,
+    pub date: Option<String>,
+    pub title: Option<String>,
+    pub copyright: Option<String>,
+    pub description: Option<String>,
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+pub fn collect_archive(folder: &Path) -> Result<Vec<ArchiveEntry>> {
+    let images = list_image_files(folder)?;
+    let mut metadata = get_all_metadata(&images)?;
+
+    let entries = images
+        .into_iter()
+        .map(|path| {
+            let metadata = metadata.remove(&path).unwrap_or_default();
+            let tag = |key: &str| match metadata.get(key) {
+                Some(serde_json::Value::String(value)) => Some(value.clone()),
+                Some(value) => Some(value.to_string()),
+                None => None,
+            };
+
+            ArchiveEntry {
+                date: date_from_file_name(&path)
+                    .or_else(|| date_from_metadata(&metadata))
+                    .map(|date| date.format("%Y-%m-%d").to_string()),
+                title: tag("Title"),
+                copyright: tag("Copyright"),
+                description: tag("Description"),
+                path,
+                metadata,
+            }
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+#[derive(Debug, Serialize)]
+pub struct CorruptImage {
+    pub path: PathBuf,
+    pub date: Option<String>,
+    pub error: String,
+}
+
+// Decoding the whole image also catches files that were cut off mid-download
+pub fn decode_error(path: &Path) -> Option<String> {
+    image::ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())
+        .and_then(|reader| reader.decode().map_err(|e| e.to_string()))
+        .err()
+}
+
+// Returns the number of checked images and the ones that could not be decoded
+pub fn verify_archive(folder: &Path) -> Result<(usize, Vec<CorruptImage>)> {
+    let images = list_image_files(folder)?;
+    let checked = images.len();
+
+    let corrupt = images
+        .into_iter()
+        .filter_map(|path| {
+            let error = decode_error(&path)?;
+            Some(CorruptImage {
+                date: date_from_file_name(&path).map(|date| date.format("%Y-%m-%d").to_string()),
+                path,
+                error,
+            })
+        })
+        .collect();
+
+    Ok((checked, corrupt))
+}
+
+pub fn export_archive(folder: &Path, format: ExportFormat, output: &Path) -> Result<usize> {
+    let entries = collect_archive(folder)?;
+
+    let content = match format {
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(&entries).map_err(|e| Error::Config(e.to_string()))?
+        }
+        ExportFormat::Ndjson => {
+            let mut content = String::new();
+            for entry in &entries {
+                content.push_str(
+                    &serde_json::to_string(entry).map_err(|e| Error::Config(e.to_string()))?,
+                );
+                content.push('\n');
+            }
+            content
+        }
+        ExportFormat::Csv => {
+            let mut content = String::from("path,date,title,copyright,description\n");
+            for entry in &entries {
+                let fields = [
+                    entry.path.to_string_lossy().to_string(),
+                    entry.date.clone().unwrap_or_default(),
+                    entry.title.clone().unwrap_or_default(),
+                    entry.copyright.clone().unwrap_or_default(),
+                    entry.description.clone().unwrap_or_default(),
+                ];
+                let row: Vec<String> = fields.iter().map(|field| csv_escape(field)).collect();
+                content.push_str(&row.join(","));
+                content.push('\n');
+            }
+            content
+        }
+    };
+
+    write(output, content).map_err(|e| {
+        Error::Config(format!(
+            "Could not write export to {}: {}",
+            output.display(),
+            e
+        ))
+    })?;
+    Ok(entries.len())
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,26 @@
+use apod_wallpaper::{WallpaperConfig, tui};
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(name = "apod-wallpaper-tui")]
+#[command(
+    version,
+    about = "APOD Wallpaper Browser, a terminal tool to switch between downloaded APOD wallpapers."
+)]
+struct Args {
+    #[arg(
+        short,
+        long,
+        help = "Folder to load wallpapers from (defaults to the config)"
+    )]
+    folder: Option<std::path::PathBuf>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let config = WallpaperConfig::load_or_default()?;
+    let save_folder = args.folder.unwrap_or(config.save_folder);
+
+    tui::run_browser(save_folder)?;
+    Ok(())
+}
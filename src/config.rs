@@ -1,14 +1,183 @@
-use crate::utils::{get_cache_dir, get_config_dir};
+use crate::desktop::{DesktopOptions, TransitionConfig};
+#[cfg(feature = "download-only")]
+use crate::utils::PrunePolicy;
+use crate::utils::{acquire_lock, date_from_file_name, get_cache_dir, get_config_dir};
 use crate::{Error, Result};
 use serde::{Deserialize, Serialize};
-use std::fs::{read_to_string, write};
-use std::path::PathBuf;
+use std::fs::{read_to_string, rename, write};
+use std::path::{Path, PathBuf};
+
+// Bumped whenever WallpaperConfig::migrate() gains a step
+pub const CONFIG_VERSION: u32 = 1;
+// Also used by ApodClient when no config is given
+pub const DEFAULT_MAX_RANGE_DAYS: usize = 100;
+
+pub const THEMES: &[&str] = &[
+    "Dark",
+    "Light",
+    "Dracula",
+    "Nord",
+    "SolarizedLight",
+    "SolarizedDark",
+    "GruvboxLight",
+    "GruvboxDark",
+    "CatppuccinLatte",
+    "CatppuccinFrappe",
+    "CatppuccinMacchiato",
+    "CatppuccinMocha",
+    "TokyoNight",
+    "TokyoNightStorm",
+    "TokyoNightLight",
+    "KanagawaWave",
+    "KanagawaDragon",
+    "KanagawaLotus",
+    "Moonfly",
+    "Nightfly",
+    "Oxocarbon",
+];
+
+pub fn resolve_theme(name: &str) -> Result<&'static str> {
+    let query = name.to_lowercase().replace([' ', '-', '_'], "");
+    if let Some(theme) = THEMES.iter().find(|theme| theme.to_lowercase() == query) {
+        return Ok(theme);
+    }
+
+    let matches: Vec<&str> = THEMES
+        .iter()
+        .filter(|theme| theme.to_lowercase().contains(&query))
+        .copied()
+        .collect();
+    match matches.as_slice() {
+        [theme] => Ok(theme),
+        [] => Err(Error::Config(format!(
+            "Unknown theme '{}', available themes: {}",
+            name,
+            THEMES.join(", ")
+        ))),
+        _ => Err(Error::Config(format!(
+            "Theme '{}' is ambiguous, did you mean one of: {}",
+            name,
+            matches.join(", ")
+        ))),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FolderStructure {
+    #[default]
+    Flat,
+    ByYear,
+    ByMonth,
+}
+
+impl FolderStructure {
+    pub fn subfolder(&self, date: &str) -> PathBuf {
+        let (Some(year), Some(month)) = (date.get(..4), date.get(5..7)) else {
+            return PathBuf::new();
+        };
+        match self {
+            FolderStructure::Flat => PathBuf::new(),
+            FolderStructure::ByYear => PathBuf::from(year),
+            FolderStructure::ByMonth => PathBuf::from(year).join(month),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThumbnailFormat {
+    // Same format as the original image
+    #[default]
+    Source,
+    Jpeg,
+}
+
+// What the default command does when the newest APOD is not an image, e.g. a video
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnNoImage {
+    // Use the most recent older APOD that is an image
+    #[default]
+    WalkBack,
+    KeepCurrent,
+    // A random image from the save folder
+    Random,
+    Fail,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationBody {
+    #[default]
+    Full,
+    TitleOnly,
+    // Title and the first sentence of the explanation
+    Short,
+}
+
+impl NotificationBody {
+    pub fn format(&self, title: &str, explanation: &str) -> String {
+        let explanation = match self {
+            NotificationBody::Full => explanation.trim(),
+            NotificationBody::TitleOnly => "",
+            NotificationBody::Short => first_sentence(explanation),
+        };
+        if explanation.is_empty() {
+            title.to_string()
+        } else {
+            format!("{}\n{}", title, explanation)
+        }
+    }
+}
+
+fn first_sentence(text: &str) -> &str {
+    let text = text.trim();
+    let end = text
+        .match_indices(['.', '!', '?'])
+        .map(|(i, _)| i + 1)
+        .find(|&end| text[end..].is_empty() || text[end..].starts_with(char::is_whitespace))
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorBackend {
+    Pywal,
+    Wallust,
+    Matugen,
+}
+
+impl ColorBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorBackend::Pywal => "pywal",
+            ColorBackend::Wallust => "wallust",
+            ColorBackend::Matugen => "matugen",
+        }
+    }
+
+    pub fn command(&self) -> &'static str {
+        match self {
+            ColorBackend::Pywal => "wal",
+            ColorBackend::Wallust => "wallust",
+            ColorBackend::Matugen => "matugen",
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct WallpaperConfig {
+    // Configs written before versioning was added have no version and count as 0
+    #[serde(default)]
+    pub version: u32,
     #[serde(default = "default_save_folder")]
     pub save_folder: PathBuf,
+    // Named collections, selected by their folder name with --folder-name
+    #[serde(default)]
+    pub folders: Vec<PathBuf>,
     #[serde(default)]
     pub multi_monitor: bool,
     #[serde(default)]
@@ -19,53 +188,357 @@ pub struct WallpaperConfig {
     pub wallust: bool,
     #[serde(default = "default_theme")]
     pub theme: String,
+    // Replaces https://api.nasa.gov/planetary/apod and the APOD_API_BASE variable
+    #[serde(default)]
+    pub api_base: Option<String>,
+    #[serde(default)]
+    pub fallback_sources: Vec<String>,
+    // Other languages are fetched from explanation_mirror, falling back to English
+    #[serde(default)]
+    pub explanation_language: Option<String>,
+    #[serde(default)]
+    pub explanation_mirror: Option<String>,
+    #[serde(default)]
+    pub swww_resize: Option<String>,
+    #[serde(default)]
+    pub swww_fill_color: Option<String>,
+    // "none", "fade" or a swww transition type, the duration is in seconds
+    #[serde(default)]
+    pub transition: Option<String>,
+    #[serde(default)]
+    pub transition_duration: Option<f32>,
+    #[serde(default)]
+    pub lockscreen_path: Option<PathBuf>,
+    #[serde(default)]
+    pub notification_body: NotificationBody,
+    #[serde(default)]
+    pub on_no_image: OnNoImage,
+    // Always points at the current wallpaper, set to null to disable
+    #[serde(default = "default_latest_symlink")]
+    pub latest_symlink: Option<PathBuf>,
+    #[serde(default)]
+    pub prefetch_days: usize,
+    // Longer download ranges need --yes
+    #[serde(default = "default_max_range_days")]
+    pub max_range_days: usize,
+    // The oldest images are deleted after downloads once either limit is exceeded
+    #[serde(default)]
+    pub max_archive_size_mb: Option<u64>,
+    #[serde(default)]
+    pub max_archive_count: Option<usize>,
+    #[serde(default)]
+    pub skip_metadata: bool,
+    // Animated APODs are set as their first frame, this also keeps the original GIF
+    #[serde(default)]
+    pub keep_gif: bool,
+    #[serde(default)]
+    pub persist_hyprpaper_config: bool,
+    #[serde(default)]
+    pub keep_hyprpaper_preloads: bool,
+    #[serde(default)]
+    pub preferred_wallpaper_tool: Option<String>,
+    #[serde(default)]
+    pub folder_structure: FolderStructure,
+    #[serde(default)]
+    pub color_source_monitor: Option<String>,
+    // Every installed backend in the list runs, pywal and wallust mirror their entries
+    #[serde(default)]
+    pub color_backends: Vec<ColorBackend>,
+    #[serde(default = "default_prefer_hd")]
+    pub prefer_hd: bool,
+    #[serde(default = "default_grid_zoom")]
+    pub grid_zoom: f32,
+    #[serde(default)]
+    pub thumbnail_format: ThumbnailFormat,
+    #[serde(default = "default_thumbnail_quality")]
+    pub thumbnail_quality: u8,
+    // Images decoded at once while generating thumbnails, defaults to the number of CPUs
+    #[serde(default)]
+    pub thumbnail_threads: Option<usize>,
+    // Scales the whole switcher UI, APOD_WALLPAPER_SCALE takes precedence
+    #[serde(default)]
+    pub gui_scale: Option<f64>,
+    // Seconds the switcher waits for the wallpaper tool before giving up
+    #[serde(default = "default_gui_timeout")]
+    pub gui_timeout: u64,
+    // Dates (YYYY-MM-DD) whose images are never picked automatically
+    #[serde(default)]
+    pub blacklist: Vec<String>,
+    // While set, scheduled runs keep downloading but do not replace the wallpaper
+    #[serde(default)]
+    pub pinned_wallpaper: Option<PathBuf>,
+    #[serde(default)]
+    pub refresh_time: Option<String>,
+    #[serde(default)]
+    pub quiet_hours: Option<String>,
+}
+
+fn default_prefer_hd() -> bool {
+    true
 }
 
+fn default_grid_zoom() -> f32 {
+    1.0
+}
+
+fn default_max_range_days() -> usize {
+    DEFAULT_MAX_RANGE_DAYS
+}
+
+fn default_latest_symlink() -> Option<PathBuf> {
+    get_cache_dir()
+        .ok()
+        .map(|cache_dir| cache_dir.join("latest"))
+}
+
+fn default_thumbnail_quality() -> u8 {
+    85
+}
+
+fn default_gui_timeout() -> u64 {
+    10
+}
+
+pub fn folder_name(folder: &Path) -> String {
+    folder
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| folder.to_string_lossy().to_string())
+}
+
+// An empty theme lets the switcher follow the system dark/light preference
 fn default_theme() -> String {
-    "Dark".to_string()
+    String::new()
 }
 
 fn default_save_folder() -> PathBuf {
     dirs::picture_dir()
         .or_else(|| {
-            std::env::var("HOME").ok().map(|home| {
-                PathBuf::from(home).join("Pictures")
-            })
-        })
-        .unwrap_or_else(|| {
-            get_cache_dir().unwrap_or_else(|_| {
-                panic!(
-                    "{}",
-                    Error::Config("Could not find picture directory. Please set HOME, XDG_PICTURES_DIR, or XDG_CACHE_HOME environment variable.".to_string())
-                )
-            })
+            std::env::var("HOME")
+                .ok()
+                .map(|home| PathBuf::from(home).join("Pictures"))
         })
+        // Never fail here, --folder or save_folder can still point somewhere else
+        .unwrap_or_else(|| get_cache_dir().unwrap_or_else(|_| std::env::temp_dir()))
         .join(PathBuf::from("apod"))
 }
 
 impl Default for WallpaperConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             save_folder: default_save_folder(),
+            folders: Vec::new(),
             multi_monitor: false,
             random: false,
             pywal: false,
             wallust: false,
             theme: default_theme(),
+            api_base: None,
+            fallback_sources: Vec::new(),
+            explanation_language: None,
+            explanation_mirror: None,
+            swww_resize: None,
+            swww_fill_color: None,
+            transition: None,
+            transition_duration: None,
+            lockscreen_path: None,
+            notification_body: NotificationBody::Full,
+            on_no_image: OnNoImage::WalkBack,
+            latest_symlink: default_latest_symlink(),
+            prefetch_days: 0,
+            max_range_days: default_max_range_days(),
+            max_archive_size_mb: None,
+            max_archive_count: None,
+            skip_metadata: false,
+            keep_gif: false,
+            persist_hyprpaper_config: false,
+            keep_hyprpaper_preloads: false,
+            preferred_wallpaper_tool: None,
+            folder_structure: FolderStructure::Flat,
+            color_source_monitor: None,
+            color_backends: Vec::new(),
+            prefer_hd: default_prefer_hd(),
+            grid_zoom: default_grid_zoom(),
+            thumbnail_format: ThumbnailFormat::Source,
+            thumbnail_quality: default_thumbnail_quality(),
+            thumbnail_threads: None,
+            gui_scale: None,
+            gui_timeout: default_gui_timeout(),
+            blacklist: Vec::new(),
+            pinned_wallpaper: None,
+            refresh_time: None,
+            quiet_hours: None,
         }
     }
 }
 
 impl WallpaperConfig {
+    pub fn desktop_options(&self) -> DesktopOptions {
+        DesktopOptions {
+            swww_resize: self.swww_resize.clone(),
+            swww_fill_color: self.swww_fill_color.clone(),
+            persist_hyprpaper: self.persist_hyprpaper_config,
+            keep_hyprpaper_preloads: self.keep_hyprpaper_preloads,
+            preferred_wallpaper_tool: self.preferred_wallpaper_tool.clone(),
+            transition: TransitionConfig {
+                kind: self.transition.clone(),
+                duration: self.transition_duration,
+            },
+        }
+    }
+
+    #[cfg(feature = "download-only")]
+    pub fn prune_policy(&self) -> PrunePolicy {
+        PrunePolicy {
+            max_size_mb: self.max_archive_size_mb,
+            max_count: self.max_archive_count,
+        }
+    }
+
+    // Defaults to the number of CPUs
+    pub fn thumbnail_thread_count(&self) -> usize {
+        self.thumbnail_threads
+            .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    pub fn folder_by_name(&self, name: &str) -> Result<PathBuf> {
+        self.folders
+            .iter()
+            .find(|folder| folder_name(folder).eq_ignore_ascii_case(name))
+            .cloned()
+            .ok_or_else(|| {
+                let names: Vec<String> = self
+                    .folders
+                    .iter()
+                    .map(|folder| folder_name(folder))
+                    .collect();
+                Error::Config(if names.is_empty() {
+                    format!("Unknown folder '{}', no folders are configured", name)
+                } else {
+                    format!(
+                        "Unknown folder '{}', available folders: {}",
+                        name,
+                        names.join(", ")
+                    )
+                })
+            })
+    }
+
+    #[cfg(feature = "download-only")]
+    pub fn blacklisted_dates(&self) -> Vec<chrono::NaiveDate> {
+        self.blacklist
+            .iter()
+            .filter_map(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .collect()
+    }
+
+    pub fn is_blacklisted(&self, image_path: &Path) -> bool {
+        date_from_file_name(image_path).is_some_and(|date| {
+            self.blacklist
+                .contains(&date.format("%Y-%m-%d").to_string())
+        })
+    }
+
+    // Returns false when the file name does not start with a date
+    pub fn blacklist_image(&mut self, image_path: &Path) -> bool {
+        let Some(date) = date_from_file_name(image_path) else {
+            return false;
+        };
+        let date = date.format("%Y-%m-%d").to_string();
+        if !self.blacklist.contains(&date) {
+            self.blacklist.push(date);
+        }
+        true
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let mut raw = serde_json::to_value(&*self).map_err(|e| Error::Config(e.to_string()))?;
+        let Some(field) = raw.get_mut(key) else {
+            return Err(Error::Config(format!("Unknown config key '{}'", key)));
+        };
+
+        *field = match key {
+            // An empty theme follows the system preference
+            "theme" if value.is_empty() => serde_json::Value::from(""),
+            "theme" => serde_json::Value::from(resolve_theme(value)?),
+            // Plain strings do not need to be quoted on the command line
+            _ => serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::from(value)),
+        };
+        *self = serde_json::from_value(raw)
+            .map_err(|e| Error::Config(format!("Invalid value for '{}': {}", key, e)))?;
+        match key {
+            "pywal" | "wallust" => {
+                let (pywal, wallust) = (self.pywal, self.wallust);
+                self.set_color_backend(ColorBackend::Pywal, pywal);
+                self.set_color_backend(ColorBackend::Wallust, wallust);
+            }
+            "color_backends" => self.sync_color_switches(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // The pywal and wallust switches always mirror whether they are part of color_backends
+    pub fn set_color_backend(&mut self, backend: ColorBackend, enabled: bool) {
+        if !enabled {
+            self.color_backends.retain(|listed| *listed != backend);
+        } else if !self.color_backends.contains(&backend) {
+            self.color_backends.push(backend);
+        }
+        self.sync_color_switches();
+    }
+
+    fn sync_color_switches(&mut self) {
+        self.pywal = self.color_backends.contains(&ColorBackend::Pywal);
+        self.wallust = self.color_backends.contains(&ColorBackend::Wallust);
+    }
+
+    // Upgrades configs written by older versions one step at a time
+    pub fn migrate(&mut self) {
+        if self.version < 1 {
+            // The pywal and wallust switches became entries of color_backends
+            for (enabled, backend) in [
+                (self.pywal, ColorBackend::Pywal),
+                (self.wallust, ColorBackend::Wallust),
+            ] {
+                if enabled && !self.color_backends.contains(&backend) {
+                    self.color_backends.push(backend);
+                }
+            }
+            self.sync_color_switches();
+            self.version = 1;
+        }
+    }
+
     pub fn load_or_default() -> Result<Self> {
         let config_dir = get_config_dir()?;
         let config_path = config_dir.join(PathBuf::from("config.json"));
 
-        if config_path.exists() {
-            let content = read_to_string(&config_path)?;
-            let config: Self =
+        let content = {
+            let _lock = acquire_lock("config")?;
+            if config_path.exists() {
+                Some(read_to_string(&config_path)?)
+            } else {
+                None
+            }
+        };
+
+        if let Some(content) = content {
+            let raw: serde_json::Value =
                 serde_json::from_str(&content).map_err(|e| Error::Config(e.to_string()))?;
-            config.save()?;
+            let mut config: Self =
+                serde_json::from_value(raw.clone()).map_err(|e| Error::Config(e.to_string()))?;
+            config.migrate();
+
+            // Only rewrite the file when it was migrated or defaults were filled in
+            let migrated =
+                serde_json::to_value(&config).map_err(|e| Error::Config(e.to_string()))?;
+            if migrated != raw {
+                config.save()?;
+            }
             Ok(config)
         } else {
             let config = Self::default();
@@ -75,12 +548,77 @@ impl WallpaperConfig {
     }
 
     pub fn save(&self) -> Result<()> {
+        let _lock = acquire_lock("config")?;
         let config_dir = get_config_dir()?;
         let config_path = config_dir.join(PathBuf::from("config.json"));
 
         let content =
             serde_json::to_string_pretty(self).map_err(|e| Error::Config(e.to_string()))?;
-        write(&config_path, content)?;
+        let temp_path = config_dir.join(PathBuf::from("config.json.tmp"));
+        write(&temp_path, content)?;
+        rename(&temp_path, &config_path)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_v0(json: &str) -> WallpaperConfig {
+        let mut config: WallpaperConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.version, 0);
+        config.migrate();
+        config
+    }
+
+    #[test]
+    fn migrate_folds_color_switches_into_backends() {
+        let config = load_v0(r#"{ "pywal": true, "wallust": true, "random": true }"#);
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(
+            config.color_backends,
+            vec![ColorBackend::Pywal, ColorBackend::Wallust]
+        );
+        assert!(config.pywal);
+        assert!(config.wallust);
+        assert!(config.random);
+    }
+
+    #[test]
+    fn migrate_keeps_listed_backends() {
+        let config = load_v0(r#"{ "wallust": true, "color_backends": ["matugen"] }"#);
+
+        assert_eq!(
+            config.color_backends,
+            vec![ColorBackend::Matugen, ColorBackend::Wallust]
+        );
+        assert!(!config.pywal);
+        assert!(config.wallust);
+    }
+
+    #[test]
+    fn migrate_leaves_current_configs_alone() {
+        let mut config = WallpaperConfig::default();
+        config.migrate();
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(config.color_backends.is_empty());
+    }
+
+    #[test]
+    fn color_switches_follow_backends() {
+        let mut config = WallpaperConfig::default();
+        config.set_color_backend(ColorBackend::Pywal, true);
+        assert!(config.pywal);
+        assert_eq!(config.color_backends, vec![ColorBackend::Pywal]);
+
+        config.set("color_backends", r#"["wallust"]"#).unwrap();
+        assert!(!config.pywal);
+        assert!(config.wallust);
+
+        config.set("wallust", "false").unwrap();
+        assert!(config.color_backends.is_empty());
+    }
+}
@@ -1,14 +1,33 @@
-use super::WallpaperManager;
-use crate::utils::command_exists;
+use super::{DesktopOptions, Screen, WallpaperManager, gsettings_prefers_dark, parse_swww_query};
+use crate::utils::{command_exists, get_cache_dir};
 use crate::{Error, Result};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::UnixStream;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::sync::OnceLock;
+use std::process::{Child, Command, Output};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
 #[cfg(any(feature = "cli", feature = "gui"))]
 use crate::utils::send_notification;
 
 static WALLPAPER_TOOLS: OnceLock<WallpaperTools> = OnceLock::new();
+static SWAYBG_CHILDREN: Mutex<Vec<Child>> = Mutex::new(Vec::new());
+
+const HYPRPAPER_ATTEMPTS: u32 = 5;
+const HYPRPAPER_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+// swaybg keeps running to show the wallpaper, so the processes started here are stopped on shutdown
+pub fn kill_spawned_children() {
+    let Ok(mut children) = SWAYBG_CHILDREN.lock() else {
+        return;
+    };
+    for mut child in children.drain(..) {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
 
 struct WallpaperTools {
     has_hyprpaper: bool,
@@ -18,20 +37,10 @@ struct WallpaperTools {
 
 impl WallpaperTools {
     fn new() -> Self {
-        let has_hyprpaper = command_exists("hyprpaper");
-        let has_swww = command_exists("swww");
-        let has_swaybg = command_exists("swaybg");
-
-        if !has_hyprpaper && !has_swww && !has_swaybg {
-            panic!(
-                "No supported wallpaper tool found. Please install one of hyprpaper, swww, or swaybg."
-            );
-        }
-
         Self {
-            has_hyprpaper,
-            has_swww,
-            has_swaybg,
+            has_hyprpaper: command_exists("hyprpaper"),
+            has_swww: command_exists("swww"),
+            has_swaybg: command_exists("swaybg"),
         }
     }
 
@@ -40,17 +49,198 @@ impl WallpaperTools {
     }
 }
 
-pub struct HyprlandManager {}
+fn is_socket_error(output: &Output) -> bool {
+    // hyprctl reports a missing IPC socket on stdout, sometimes with a zero exit code
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+    .to_lowercase();
+    text.contains("couldn't connect") || text.contains("connection refused")
+}
+
+fn persist_hyprpaper_config(path: &Path, screen: Option<&str>) -> Result<()> {
+    let config_path = dirs::config_dir()
+        .ok_or_else(|| Error::DesktopEnv("Could not find config directory".to_string()))?
+        .join("hypr")
+        .join("hyprpaper.conf");
+
+    let content = std::fs::read_to_string(&config_path).unwrap_or_default();
+    let content = update_hyprpaper_config(&content, path, screen);
+
+    if let Some(parent) = config_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&config_path, content)?;
+    Ok(())
+}
+
+fn clear_hyprpaper_config() -> Result<()> {
+    let config_path = dirs::config_dir()
+        .ok_or_else(|| Error::DesktopEnv("Could not find config directory".to_string()))?
+        .join("hypr")
+        .join("hyprpaper.conf");
+    let Ok(content) = std::fs::read_to_string(&config_path) else {
+        return Ok(());
+    };
+
+    let mut content: String = content
+        .lines()
+        .filter(|line| {
+            !matches!(
+                line.split_once('=').map(|(key, _)| key.trim()),
+                Some("preload" | "wallpaper")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    content.push('\n');
+    std::fs::write(&config_path, content)?;
+    Ok(())
+}
+
+fn update_hyprpaper_config(content: &str, path: &Path, screen: Option<&str>) -> String {
+    let screen = screen.unwrap_or("");
+    let key_value = |line: &str| {
+        line.split_once('=')
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+    };
+
+    let mut lines = Vec::new();
+    let mut wallpapers = Vec::new();
+    for line in content.lines() {
+        match key_value(line) {
+            // Preloads are regenerated from the wallpaper lines below
+            Some((key, _)) if key == "preload" => {}
+            Some((key, value)) if key == "wallpaper" => {
+                let (monitor, image) = value.split_once(',').unwrap_or(("", value.as_str()));
+                // A wallpaper for every monitor replaces all per-monitor entries
+                if screen.is_empty() || monitor.trim() == screen {
+                    continue;
+                }
+                wallpapers.push((monitor.trim().to_string(), image.trim().to_string()));
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+    wallpapers.push((screen.to_string(), path.to_string_lossy().to_string()));
+
+    let mut images: Vec<&String> = wallpapers.iter().map(|(_, image)| image).collect();
+    images.sort();
+    images.dedup();
+
+    for image in images {
+        lines.push(format!("preload = {}", image));
+    }
+    for (monitor, image) in &wallpapers {
+        lines.push(format!("wallpaper = {},{}", monitor, image));
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
+}
+
+fn workspace_wallpapers_path() -> Result<PathBuf> {
+    Ok(get_cache_dir()?.join("workspace-wallpapers.json"))
+}
+
+fn read_workspace_wallpapers() -> Result<HashMap<String, PathBuf>> {
+    let path = workspace_wallpapers_path()?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    serde_json::from_str(&content).map_err(|e| Error::Config(e.to_string()))
+}
+
+fn hyprctl_json(args: &[&str]) -> Option<serde_json::Value> {
+    let output = Command::new("hyprctl").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+// Maps every monitor to the name of the workspace it is showing
+fn active_workspaces() -> Vec<(String, String)> {
+    let Some(monitors) = hyprctl_json(&["monitors", "-j"]) else {
+        return Vec::new();
+    };
+    monitors
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|monitor| {
+            Some((
+                monitor["name"].as_str()?.to_string(),
+                monitor["activeWorkspace"]["name"].as_str()?.to_string(),
+            ))
+        })
+        .collect()
+}
+
+fn event_socket_path() -> Result<PathBuf> {
+    let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .map_err(|_| Error::DesktopEnv("HYPRLAND_INSTANCE_SIGNATURE is not set".to_string()))?;
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_default();
+
+    // Hyprland moved its sockets from /tmp to the runtime directory
+    [PathBuf::from(runtime_dir), PathBuf::from("/tmp")]
+        .into_iter()
+        .map(|dir| dir.join("hypr").join(&signature).join(".socket2.sock"))
+        .find(|path| path.exists())
+        .ok_or_else(|| Error::DesktopEnv("Could not find the Hyprland event socket".to_string()))
+}
+
+pub struct HyprlandManager {
+    options: DesktopOptions,
+}
 
 impl HyprlandManager {
-    pub fn new() -> Self {
-        WALLPAPER_TOOLS.get_or_init(WallpaperTools::new);
-        if !WALLPAPER_TOOLS.get().unwrap().has_any() {
-            panic!(
-                "No supported wallpaper tool found. Please install one of hyprpaper, swww, or swaybg."
-            );
+    pub fn new() -> Result<Self> {
+        Self::with_options(DesktopOptions::default())
+    }
+
+    pub fn with_options(options: DesktopOptions) -> Result<Self> {
+        let tools = WALLPAPER_TOOLS.get_or_init(WallpaperTools::new);
+        if !tools.has_any() {
+            return Err(Error::DesktopEnv(
+                "No supported wallpaper tool found. Please install one of hyprpaper, swww, or swaybg.".to_string(),
+            ));
+        }
+
+        if let Some(preferred) = &options.preferred_wallpaper_tool {
+            let installed = match preferred.as_str() {
+                "hyprpaper" => tools.has_hyprpaper,
+                "swww" => tools.has_swww,
+                "swaybg" => tools.has_swaybg,
+                _ => {
+                    return Err(Error::Config(format!(
+                        "Unknown wallpaper tool '{}', use hyprpaper, swww or swaybg",
+                        preferred
+                    )));
+                }
+            };
+            if !installed {
+                return Err(Error::DesktopEnv(format!(
+                    "Preferred wallpaper tool {} is not installed",
+                    preferred
+                )));
+            }
         }
-        Self {}
+        Ok(Self { options })
+    }
+
+    // A preferred tool replaces the hyprpaper, swww, swaybg fallback order
+    fn uses(&self, tool: &str, installed: bool) -> bool {
+        installed
+            && self
+                .options
+                .preferred_wallpaper_tool
+                .as_deref()
+                .is_none_or(|preferred| preferred == tool)
     }
 
     pub fn is_available() -> bool {
@@ -61,44 +251,105 @@ impl HyprlandManager {
 
 impl WallpaperManager for HyprlandManager {
     fn get_screens(&self) -> Vec<String> {
-        let output = Command::new("hyprctl").args(&["monitors", "-j"]).output();
+        self.get_screen_info()
+            .into_iter()
+            .map(|screen| screen.name)
+            .collect()
+    }
 
-        match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                if let Ok(monitors) = serde_json::from_str::<serde_json::Value>(&stdout) {
-                    if let Some(array) = monitors.as_array() {
-                        return array
-                            .iter()
-                            .filter_map(|m| m["name"].as_str().map(String::from))
-                            .collect();
-                    }
-                }
-                vec!["default".to_string()]
+    fn get_screen_info(&self) -> Vec<Screen> {
+        let output = Command::new("hyprctl").args(["monitors", "-j"]).output();
+
+        if let Ok(output) = output {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            if let Ok(monitors) = serde_json::from_str::<serde_json::Value>(&stdout)
+                && let Some(array) = monitors.as_array()
+            {
+                return array
+                    .iter()
+                    .filter_map(|m| {
+                        let name = m["name"].as_str()?;
+                        let width = m["width"].as_u64().unwrap_or(0) as u32;
+                        let height = m["height"].as_u64().unwrap_or(0) as u32;
+                        // Odd transforms rotate the output by 90 or 270 degrees
+                        let rotated = m["transform"].as_u64().unwrap_or(0) % 2 == 1;
+                        Some(Screen {
+                            name: name.to_string(),
+                            width: if rotated { height } else { width },
+                            height: if rotated { width } else { height },
+                            x: m["x"].as_i64().unwrap_or(0) as i32,
+                            y: m["y"].as_i64().unwrap_or(0) as i32,
+                            scale: m["scale"].as_f64().unwrap_or(1.0),
+                        })
+                    })
+                    .collect();
+            }
+        }
+
+        if WALLPAPER_TOOLS.get().unwrap().has_swww
+            && let Ok(output) = Command::new("swww").arg("query").output()
+            && output.status.success()
+        {
+            let screens = parse_swww_query(&String::from_utf8_lossy(&output.stdout));
+            if !screens.is_empty() {
+                return screens;
             }
-            Err(_) => vec!["default".to_string()],
         }
+
+        vec![Screen::named("default")]
+    }
+
+    fn supports_per_screen(&self) -> bool {
+        let tools = WALLPAPER_TOOLS.get().unwrap();
+        self.uses("hyprpaper", tools.has_hyprpaper) || self.uses("swww", tools.has_swww)
     }
 
     fn set_wallpaper(&self, path: &Path, screen: Option<&str>) -> Result<()> {
         let path_str = path.to_string_lossy();
         let tools = WALLPAPER_TOOLS.get().unwrap();
 
-        if tools.has_hyprpaper {
-            let command = match screen {
-                Some(screen) => format!(
-                    "hyprpaper preload {} && hyprctl hyprpaper wallpaper \"{},{}\"",
-                    path_str, screen, path_str
-                ),
-                None => format!(
-                    "hyprpaper preload {} && hyprctl hyprpaper wallpaper {}",
-                    path_str, path_str
-                ),
+        if self.uses("hyprpaper", tools.has_hyprpaper) {
+            // An empty monitor name applies the image to every monitor
+            let target = format!("{},{}", screen.unwrap_or_default(), path_str);
+            let run = || -> Result<Output> {
+                let preload = Command::new("hyprctl")
+                    .args(["hyprpaper", "preload"])
+                    .arg(path)
+                    .output()?;
+                if !preload.status.success() || is_socket_error(&preload) {
+                    return Ok(preload);
+                }
+                Ok(Command::new("hyprctl")
+                    .args(["hyprpaper", "wallpaper"])
+                    .arg(&target)
+                    .output()?)
             };
 
-            let output = Command::new("sh").arg("-c").arg(command).output()?;
+            // hyprpaper may still be starting when launched from exec-once at login
+            let mut attempt = 1;
+            let (output, ready) = loop {
+                let output = run()?;
+                let ready = !is_socket_error(&output);
+                if ready || attempt >= HYPRPAPER_ATTEMPTS {
+                    break (output, ready);
+                }
+                attempt += 1;
+                std::thread::sleep(HYPRPAPER_RETRY_DELAY);
+            };
 
-            if output.status.success() {
+            if output.status.success() && ready {
+                // Only images that are not shown on any monitor get unloaded
+                if !self.options.keep_hyprpaper_preloads
+                    && let Ok(output) = Command::new("hyprctl")
+                        .args(["hyprpaper", "unload", "all"])
+                        .output()
+                    && !output.status.success()
+                {
+                    eprintln!(
+                        "hyprctl hyprpaper unload command failed: {}",
+                        String::from_utf8_lossy(&output.stderr)
+                    );
+                }
                 return Ok(());
             } else {
                 eprintln!(
@@ -108,12 +359,24 @@ impl WallpaperManager for HyprlandManager {
             }
         }
 
-        if tools.has_swww {
-            let command = match screen {
-                Some(screen) => format!("swww img {} -o {} -t grow", path_str, screen),
-                None => format!("swww img {} -t grow", path_str),
-            };
-            let output = Command::new("sh").arg("-c").arg(command).output()?;
+        if self.uses("swww", tools.has_swww) {
+            let transition = self.options.transition.kind.as_deref().unwrap_or("grow");
+            // Config values are passed as separate arguments, never through a shell
+            let mut command = Command::new("swww");
+            command.args(["img", path_str.as_ref(), "-t", transition]);
+            if let Some(screen) = screen {
+                command.args(["-o", screen]);
+            }
+            if let Some(duration) = self.options.transition.duration {
+                command.args(["--transition-duration", &duration.to_string()]);
+            }
+            if let Some(resize) = &self.options.swww_resize {
+                command.args(["--resize", resize]);
+            }
+            if let Some(fill_color) = &self.options.swww_fill_color {
+                command.args(["--fill-color", fill_color]);
+            }
+            let output = command.output()?;
 
             if output.status.success() {
                 return Ok(());
@@ -125,12 +388,15 @@ impl WallpaperManager for HyprlandManager {
             }
         }
 
-        if tools.has_swaybg {
+        if self.uses("swaybg", tools.has_swaybg) {
             let output = Command::new("swaybg")
-                .args(["-i", &path_str.to_string()])
+                .args(["-i", path_str.as_ref()])
                 .spawn();
 
-            if output.is_ok() {
+            if let Ok(child) = output {
+                if let Ok(mut children) = SWAYBG_CHILDREN.lock() {
+                    children.push(child);
+                }
                 return Ok(());
             } else {
                 eprintln!("swaybg command failed to start");
@@ -142,12 +408,96 @@ impl WallpaperManager for HyprlandManager {
         ))
     }
 
+    fn prefers_dark(&self) -> Option<bool> {
+        gsettings_prefers_dark()
+    }
+
+    fn get_workspaces(&self) -> Vec<String> {
+        let Some(workspaces) = hyprctl_json(&["workspaces", "-j"]) else {
+            return Vec::new();
+        };
+        let mut workspaces: Vec<(i64, String)> = workspaces
+            .as_array()
+            .into_iter()
+            .flatten()
+            // Special workspaces like the scratchpad have negative ids
+            .filter(|workspace| workspace["id"].as_i64().is_some_and(|id| id > 0))
+            .filter_map(|workspace| {
+                Some((
+                    workspace["id"].as_i64()?,
+                    workspace["name"].as_str()?.to_string(),
+                ))
+            })
+            .collect();
+        workspaces.sort();
+        workspaces.into_iter().map(|(_, name)| name).collect()
+    }
+
+    fn set_wallpaper_for_workspace(&self, path: &Path, workspace: &str) -> Result<()> {
+        let mut wallpapers = read_workspace_wallpapers()?;
+        wallpapers.insert(workspace.to_string(), path.to_path_buf());
+        let content =
+            serde_json::to_string_pretty(&wallpapers).map_err(|e| Error::Config(e.to_string()))?;
+        std::fs::write(workspace_wallpapers_path()?, content)?;
+
+        for (monitor, active) in active_workspaces() {
+            if active == workspace {
+                self.set_wallpaper(path, Some(&monitor))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn follow_workspaces(&self) -> Result<()> {
+        let stream = UnixStream::connect(event_socket_path()?)?;
+        let mut applied: HashMap<String, PathBuf> = HashMap::new();
+
+        let apply = |applied: &mut HashMap<String, PathBuf>| -> Result<()> {
+            // Re-read the mapping so new assignments apply without a restart
+            let wallpapers = read_workspace_wallpapers()?;
+            for (monitor, workspace) in active_workspaces() {
+                if let Some(path) = wallpapers.get(&workspace)
+                    && applied.get(&monitor) != Some(path)
+                    && path.exists()
+                {
+                    self.set_wallpaper(path, Some(&monitor))?;
+                    applied.insert(monitor, path.clone());
+                }
+            }
+            Ok(())
+        };
+
+        apply(&mut applied)?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            let event = line
+                .split_once(">>")
+                .map_or(line.as_str(), |(event, _)| event);
+            if matches!(
+                event,
+                "workspace" | "workspacev2" | "focusedmon" | "moveworkspace"
+            ) && let Err(e) = apply(&mut applied)
+            {
+                eprintln!("Failed to apply workspace wallpaper: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    fn persist_wallpaper(&self, path: &Path, screen: Option<&str>) -> Result<()> {
+        let tools = WALLPAPER_TOOLS.get().unwrap();
+        if self.options.persist_hyprpaper && self.uses("hyprpaper", tools.has_hyprpaper) {
+            persist_hyprpaper_config(path, screen)?;
+        }
+        Ok(())
+    }
+
     fn get_wallpaper(&self, screen: Option<&str>) -> Result<Option<PathBuf>> {
         let tools = WALLPAPER_TOOLS.get().unwrap();
 
-        if tools.has_hyprpaper {
+        if self.uses("hyprpaper", tools.has_hyprpaper) {
             let output = Command::new("hyprctl")
-                .args(&["hyprpaper", "wallpaper"])
+                .args(["hyprpaper", "wallpaper"])
                 .output()?;
 
             if output.status.success() {
@@ -164,7 +514,7 @@ impl WallpaperManager for HyprlandManager {
             }
         }
 
-        if tools.has_swww {
+        if self.uses("swww", tools.has_swww) {
             let output = Command::new("swww").arg("query").output()?;
 
             if output.status.success() {
@@ -198,7 +548,7 @@ impl WallpaperManager for HyprlandManager {
             }
         }
 
-        if tools.has_swaybg {
+        if self.uses("swaybg", tools.has_swaybg) {
             return Ok(None);
         }
 
@@ -207,10 +557,70 @@ impl WallpaperManager for HyprlandManager {
         ))
     }
 
-    fn notify(&self, title: &str, message: &str, image: Option<&Path>) -> Result<()> {
+    fn clear_wallpaper(&self) -> Result<()> {
+        let tools = WALLPAPER_TOOLS.get().unwrap();
+        let mut cleared = false;
+
+        if self.uses("hyprpaper", tools.has_hyprpaper) {
+            let output = Command::new("hyprctl")
+                .args(["hyprpaper", "unload", "all"])
+                .output()?;
+            if output.status.success() {
+                cleared = true;
+                if self.options.persist_hyprpaper
+                    && let Err(e) = clear_hyprpaper_config()
+                {
+                    eprintln!("Failed to update hyprpaper.conf: {}", e);
+                }
+            } else {
+                eprintln!(
+                    "hyprctl hyprpaper unload command failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+        }
+
+        if self.uses("swww", tools.has_swww) {
+            let output = Command::new("swww").arg("clear").output()?;
+            if output.status.success() {
+                cleared = true;
+            } else {
+                eprintln!(
+                    "swww clear command failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+        }
+
+        // swaybg has no IPC, the wallpaper goes away with the process
+        if self.uses("swaybg", tools.has_swaybg)
+            && let Ok(status) = Command::new("pkill").args(["-x", "swaybg"]).status()
+            && status.success()
+        {
+            cleared = true;
+        }
+
+        if cleared {
+            Ok(())
+        } else {
+            Err(Error::DesktopEnv(
+                "Failed to clear wallpaper. No supported wallpaper tool (hyprpaper, swww, or swaybg) is running".to_string(),
+            ))
+        }
+    }
+
+    fn notify(
+        &self,
+        title: &str,
+        message: &str,
+        image: Option<&Path>,
+        link: Option<&str>,
+    ) -> Result<()> {
+        #[cfg(not(any(feature = "cli", feature = "gui")))]
+        let _ = (title, message, image, link);
         #[cfg(any(feature = "cli", feature = "gui"))]
         {
-            send_notification(title, message, image)?;
+            send_notification(title, message, image, link)?;
         }
         Ok(())
     }
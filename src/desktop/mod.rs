@@ -2,31 +2,237 @@ use crate::Result;
 use std::path::{Path, PathBuf};
 
 pub mod hyprland;
+#[cfg(feature = "dbus")]
 pub mod plasma;
+#[cfg(feature = "dbus")]
+pub mod portal;
+
+#[derive(Debug, Clone, Default)]
+pub struct DesktopOptions {
+    pub swww_resize: Option<String>,
+    pub swww_fill_color: Option<String>,
+    pub persist_hyprpaper: bool,
+    pub keep_hyprpaper_preloads: bool,
+    pub preferred_wallpaper_tool: Option<String>,
+    pub transition: TransitionConfig,
+}
+
+// Each backend maps this to what it supports, swaybg and hyprpaper have no transitions
+#[derive(Debug, Clone, Default)]
+pub struct TransitionConfig {
+    // "none" disables the transition, other names are passed to swww as transition type
+    pub kind: Option<String>,
+    pub duration: Option<f32>,
+}
+
+impl TransitionConfig {
+    pub fn is_disabled(&self) -> bool {
+        self.kind.as_deref() == Some("none")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Screen {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    pub scale: f64,
+}
+
+impl Screen {
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            width: 0,
+            height: 0,
+            x: 0,
+            y: 0,
+            scale: 1.0,
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> Option<f64> {
+        if self.width == 0 || self.height == 0 {
+            None
+        } else {
+            Some(self.width as f64 / self.height as f64)
+        }
+    }
+}
 
 pub trait WallpaperManager {
     fn get_screens(&self) -> Vec<String>;
+    fn get_screen_info(&self) -> Vec<Screen> {
+        self.get_screens()
+            .iter()
+            .map(|name| Screen::named(name))
+            .collect()
+    }
+    fn supports_per_screen(&self) -> bool {
+        false
+    }
+    fn prefers_dark(&self) -> Option<bool> {
+        None
+    }
     fn set_wallpaper(&self, path: &Path, screen: Option<&str>) -> Result<()>;
+    fn get_workspaces(&self) -> Vec<String> {
+        Vec::new()
+    }
+    fn set_wallpaper_for_workspace(&self, _path: &Path, _workspace: &str) -> Result<()> {
+        Err(crate::Error::DesktopEnv(
+            "Per-workspace wallpapers are not supported on this desktop environment".to_string(),
+        ))
+    }
+    // Blocks and applies the stored per-workspace wallpapers whenever the workspace changes
+    fn follow_workspaces(&self) -> Result<()> {
+        Err(crate::Error::DesktopEnv(
+            "Per-workspace wallpapers are not supported on this desktop environment".to_string(),
+        ))
+    }
+    // Makes a wallpaper survive a restart of the wallpaper tool, only some backends need this
+    fn persist_wallpaper(&self, _path: &Path, _screen: Option<&str>) -> Result<()> {
+        Ok(())
+    }
     fn get_wallpaper(&self, screen: Option<&str>) -> Result<Option<PathBuf>>;
-    fn notify(&self, title: &str, message: &str, image: Option<&Path>) -> Result<()>;
+    fn clear_wallpaper(&self) -> Result<()>;
+    fn notify(
+        &self,
+        title: &str,
+        message: &str,
+        image: Option<&Path>,
+        link: Option<&str>,
+    ) -> Result<()>;
+}
+
+#[cfg(feature = "dbus")]
+pub(crate) fn parse_xrandr_monitors(output: &str) -> Vec<Screen> {
+    // Lines look like " 0: +*DP-1 2560/597x1440/336+0+0  DP-1"
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let _index = parts.next()?;
+            let _flags = parts.next()?;
+            let geometry = parts.next()?;
+            let name = parts.next()?;
+
+            let (size, position) = geometry.split_once('+')?;
+            let (x, y) = position.split_once('+')?;
+            let (width, height) = size.split_once('x')?;
+            let width = width.split('/').next()?.parse().ok()?;
+            let height = height.split('/').next()?.parse().ok()?;
+
+            Some(Screen {
+                name: name.to_string(),
+                width,
+                height,
+                x: x.parse().ok()?,
+                y: y.parse().ok()?,
+                scale: 1.0,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn gsettings_prefers_dark() -> Option<bool> {
+    let output = std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    match String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .trim_matches('\'')
+    {
+        "prefer-dark" => Some(true),
+        "prefer-light" => Some(false),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_swww_query(output: &str) -> Vec<Screen> {
+    // Lines look like "DP-1: 1920x1080, scale: 1, currently displaying: image: /path"
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().trim_start_matches(':').trim_start();
+            let (name, rest) = line.split_once(": ")?;
+            let mut fields = rest.split(", ");
+            let (width, height) = fields.next()?.split_once('x')?;
+            let scale = fields
+                .find_map(|field| field.strip_prefix("scale: "))
+                .and_then(|scale| scale.parse().ok())
+                .unwrap_or(1.0);
+
+            Some(Screen {
+                name: name.to_string(),
+                width: width.parse().ok()?,
+                height: height.parse().ok()?,
+                x: 0,
+                y: 0,
+                scale,
+            })
+        })
+        .collect()
 }
 
 pub fn get_wallpaper_manager() -> Result<Box<dyn WallpaperManager>> {
+    get_wallpaper_manager_with(DesktopOptions::default())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Desktop {
+    Hyprland,
+    #[cfg(feature = "dbus")]
+    Plasma,
+    #[cfg(feature = "dbus")]
+    Portal,
+}
+
+impl Desktop {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Desktop::Hyprland => "Hyprland",
+            #[cfg(feature = "dbus")]
+            Desktop::Plasma => "KDE Plasma",
+            #[cfg(feature = "dbus")]
+            Desktop::Portal => "XDG desktop portal",
+        }
+    }
+}
+
+pub fn detect_desktop() -> Option<Desktop> {
     let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
 
     match desktop.to_lowercase().as_str() {
-        "hyprland" => Ok(Box::new(hyprland::HyprlandManager::new())),
-        "kde" | "plasma" => Ok(Box::new(plasma::PlasmaManager::new())),
-        _ => {
-            if hyprland::HyprlandManager::is_available() {
-                Ok(Box::new(hyprland::HyprlandManager::new()))
-            } else if plasma::PlasmaManager::is_available() {
-                Ok(Box::new(plasma::PlasmaManager::new()))
-            } else {
-                Err(crate::Error::DesktopEnv(
-                    "No supported desktop environment found".to_string(),
-                ))
-            }
-        }
+        "hyprland" => Some(Desktop::Hyprland),
+        #[cfg(feature = "dbus")]
+        "kde" | "plasma" => Some(Desktop::Plasma),
+        _ if hyprland::HyprlandManager::is_available() => Some(Desktop::Hyprland),
+        #[cfg(feature = "dbus")]
+        _ if plasma::PlasmaManager::is_available() => Some(Desktop::Plasma),
+        // Last resort for compositors without a dedicated backend
+        #[cfg(feature = "dbus")]
+        _ if portal::PortalManager::is_available() => Some(Desktop::Portal),
+        _ => None,
+    }
+}
+
+pub fn get_wallpaper_manager_with(options: DesktopOptions) -> Result<Box<dyn WallpaperManager>> {
+    match detect_desktop() {
+        Some(Desktop::Hyprland) => Ok(Box::new(hyprland::HyprlandManager::with_options(options)?)),
+        #[cfg(feature = "dbus")]
+        Some(Desktop::Plasma) => Ok(Box::new(plasma::PlasmaManager::with_options(options)?)),
+        #[cfg(feature = "dbus")]
+        Some(Desktop::Portal) => Ok(Box::new(portal::PortalManager::new()?)),
+        None => Err(crate::Error::DesktopEnv(
+            "No supported desktop environment found".to_string(),
+        )),
     }
 }
@@ -1,73 +1,128 @@
-use super::WallpaperManager;
-use crate::utils::command_exists;
+use super::{DesktopOptions, Screen, TransitionConfig, WallpaperManager, parse_xrandr_monitors};
 use crate::{Error, Result};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use zbus::blocking::{Connection, Proxy};
 
 #[cfg(any(feature = "cli", feature = "gui"))]
-use crate::utils::send_notification;
+use crate::utils::{command_exists, send_notification};
 
-pub struct PlasmaManager;
+pub struct PlasmaManager {
+    connection: Connection,
+    transition: TransitionConfig,
+}
 
 impl PlasmaManager {
-    pub fn new() -> Self {
-        if !command_exists("qdbus") {
-            panic!("QDBus command not found. Please install qdbus.");
-        }
-        Self
+    pub fn new() -> Result<Self> {
+        Self::with_options(DesktopOptions::default())
+    }
+
+    pub fn with_options(options: DesktopOptions) -> Result<Self> {
+        let connection = Connection::session().map_err(|e| {
+            Error::DesktopEnv(format!("Could not connect to the D-Bus session bus: {}", e))
+        })?;
+        Ok(Self {
+            connection,
+            transition: options.transition,
+        })
     }
 
     pub fn is_available() -> bool {
         std::env::var("KDE_SESSION_VERSION").is_ok()
     }
+
+    fn evaluate_script(&self, script: &str) -> Result<String> {
+        let map_err =
+            |e: zbus::Error| Error::DesktopEnv(format!("Failed to run plasmashell script: {}", e));
+        Proxy::new(
+            &self.connection,
+            "org.kde.plasmashell",
+            "/PlasmaShell",
+            "org.kde.PlasmaShell",
+        )
+        .map_err(map_err)?
+        .call("evaluateScript", &(script,))
+        .map_err(map_err)
+    }
 }
 
 impl WallpaperManager for PlasmaManager {
     fn get_screens(&self) -> Vec<String> {
-        let output = Command::new("qdbus")
-            .args(&["org.kde.KWin", "/KWin", "org.kde.KWin.supportInformation"])
-            .output();
-
-        match output {
-            Ok(_) => {
-                // TODO: Parse actual screen names
-                vec!["default".to_string()]
+        self.get_screen_info()
+            .into_iter()
+            .map(|screen| screen.name)
+            .collect()
+    }
+
+    fn get_screen_info(&self) -> Vec<Screen> {
+        if let Ok(output) = Command::new("xrandr").arg("--listmonitors").output()
+            && output.status.success()
+        {
+            let screens = parse_xrandr_monitors(&String::from_utf8_lossy(&output.stdout));
+            if !screens.is_empty() {
+                return screens;
             }
-            Err(_) => vec!["default".to_string()],
         }
+
+        vec![Screen::named("default")]
     }
 
     fn set_wallpaper(&self, path: &Path, _screen: Option<&str>) -> Result<()> {
-        let path_str = path.to_string_lossy();
+        // A JSON string is also a valid JavaScript string literal, which takes care of quoting
+        let image = serde_json::to_string(&format!("file://{}", path.to_string_lossy()))
+            .map_err(|e| Error::DesktopEnv(e.to_string()))?;
+
+        // Plasma only knows a cross-fade, so every transition type maps to its duration
+        let transition = if self.transition.is_disabled() {
+            r#"d.writeConfig("TransitionAnimationDuration", 0);"#.to_string()
+        } else if let Some(duration) = self.transition.duration {
+            format!(
+                r#"d.writeConfig("TransitionAnimationDuration", {});"#,
+                (duration * 1000.0).round() as u32
+            )
+        } else {
+            String::new()
+        };
 
         // TODO: add screen support
         let script = format!(
             r#"
-            qdbus org.kde.plasmashell /PlasmaShell org.kde.PlasmaShell.evaluateScript '
             var allDesktops = desktops();
             for (i=0;i<allDesktops.length;i++) {{
                 d = allDesktops[i];
                 d.wallpaperPlugin = "org.kde.image";
                 d.currentConfigGroup = Array("Wallpaper", "org.kde.image", "General");
-                d.writeConfig("Image", "file://{}");
+                {}
+                d.writeConfig("Image", {});
             }}
-            '
             "#,
-            path_str
+            transition, image
         );
 
-        let output = Command::new("sh").arg("-c").arg(&script).output()?;
-
-        if !output.status.success() {
-            return Err(Error::DesktopEnv(format!(
-                "Failed to set wallpaper: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )));
-        }
-
+        self.evaluate_script(&script)?;
         Ok(())
     }
 
+    fn prefers_dark(&self) -> Option<bool> {
+        let scheme = ["kreadconfig6", "kreadconfig5"].iter().find_map(|cmd| {
+            let output = Command::new(cmd)
+                .args([
+                    "--file",
+                    "kdeglobals",
+                    "--group",
+                    "General",
+                    "--key",
+                    "ColorScheme",
+                ])
+                .output()
+                .ok()?;
+            let scheme = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            (output.status.success() && !scheme.is_empty()).then_some(scheme)
+        })?;
+
+        Some(scheme.to_lowercase().contains("dark"))
+    }
+
     fn get_wallpaper(&self, _screen: Option<&str>) -> Result<Option<PathBuf>> {
         //TODO: add screen support
         let script = r#"
@@ -79,20 +134,13 @@ impl WallpaperManager for PlasmaManager {
             }
         "#;
 
-        let output = Command::new("qdbus")
-            .args(&[
-                "org.kde.plasmashell",
-                "/PlasmaShell",
-                "org.kde.PlasmaShell.evaluateScript",
-                script,
-            ])
-            .output()?;
-
-        if !output.status.success() {
-            return Ok(None);
-        }
-
-        let result = String::from_utf8_lossy(&output.stdout);
+        let result = match self.evaluate_script(script) {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("{}", e);
+                return Ok(None);
+            }
+        };
         let result = result.trim();
 
         if result.is_empty() || result == "undefined" {
@@ -100,41 +148,50 @@ impl WallpaperManager for PlasmaManager {
         }
 
         // Remove "file://" prefix if present
-        let path_str = if result.starts_with("file://") {
-            &result[7..]
-        } else {
-            result
-        };
+        let path_str = result.strip_prefix("file://").unwrap_or(result);
 
         Ok(Some(PathBuf::from(path_str)))
     }
 
-    fn notify(&self, title: &str, message: &str, image: Option<&Path>) -> Result<()> {
+    fn clear_wallpaper(&self) -> Result<()> {
+        // Next is the wallpaper package Plasma ships as its default
+        self.set_wallpaper(Path::new("/usr/share/wallpapers/Next/"), None)
+    }
+
+    fn notify(
+        &self,
+        title: &str,
+        message: &str,
+        image: Option<&Path>,
+        link: Option<&str>,
+    ) -> Result<()> {
         #[cfg(any(feature = "cli", feature = "gui"))]
         {
-            if !command_exists("kdialog") {
+            // kdialog popups can't carry actions, so links always go through notify-rust
+            if link.is_some() || !command_exists("kdialog") {
                 // Fallback to notify-rust
-                return send_notification(title, message, image);
+                return send_notification(title, message, image, link);
             }
 
             let mut cmd = Command::new("kdialog");
-            cmd.args(&["--title", title, "--passivepopup", message, "5"]);
+            cmd.args(["--title", title, "--passivepopup", message, "5"]);
 
             if let Some(image_path) = image {
-                cmd.args(&["--icon", &image_path.to_string_lossy()]);
+                cmd.args(["--icon", &image_path.to_string_lossy()]);
             }
 
             let output = cmd.output()?;
 
             if !output.status.success() {
                 // Fallback to notify-rust
-                return send_notification(title, message, image);
+                return send_notification(title, message, image, link);
             }
 
             Ok(())
         }
         #[cfg(not(any(feature = "cli", feature = "gui")))]
         {
+            let _ = (title, message, image, link);
             Ok(())
         }
     }
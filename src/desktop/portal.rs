@@ -0,0 +1,151 @@
+use super::WallpaperManager;
+use crate::{Error, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedValue, Value};
+
+#[cfg(any(feature = "cli", feature = "gui"))]
+use crate::utils::send_notification;
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const WALLPAPER_INTERFACE: &str = "org.freedesktop.portal.Wallpaper";
+// Some portals ask for permission first, a request nobody answers must not block forever
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn dbus_error(e: zbus::Error) -> Error {
+    Error::DesktopEnv(format!("Desktop portal error: {}", e))
+}
+
+pub struct PortalManager {
+    connection: Connection,
+}
+
+impl PortalManager {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            connection: Connection::session().map_err(dbus_error)?,
+        })
+    }
+
+    pub fn is_available() -> bool {
+        let Ok(connection) = Connection::session() else {
+            return false;
+        };
+        Proxy::new(
+            &connection,
+            PORTAL_DESTINATION,
+            PORTAL_PATH,
+            WALLPAPER_INTERFACE,
+        )
+        .and_then(|proxy| proxy.get_property::<u32>("version"))
+        .is_ok()
+    }
+}
+
+impl WallpaperManager for PortalManager {
+    fn get_screens(&self) -> Vec<String> {
+        vec!["default".to_string()]
+    }
+
+    fn set_wallpaper(&self, path: &Path, _screen: Option<&str>) -> Result<()> {
+        let path = path.canonicalize()?;
+        let uri = format!("file://{}", path.to_string_lossy());
+
+        // The reply only carries the request handle, the outcome arrives as a Response signal
+        // on a path derived from our unique name and the handle token
+        let token = format!("apod_wallpaper_{}", std::process::id());
+        let sender = self
+            .connection
+            .unique_name()
+            .map(|name| name.trim_start_matches(':').replace('.', "_"))
+            .unwrap_or_default();
+        let request_path = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+        let request = Proxy::new(
+            &self.connection,
+            PORTAL_DESTINATION,
+            request_path,
+            "org.freedesktop.portal.Request",
+        )
+        .map_err(dbus_error)?;
+        let mut responses = request.receive_signal("Response").map_err(dbus_error)?;
+
+        let mut options: HashMap<&str, Value> = HashMap::new();
+        options.insert("handle_token", Value::from(token.as_str()));
+        options.insert("show-preview", Value::from(false));
+        options.insert("set-on", Value::from("background"));
+
+        let portal = Proxy::new(
+            &self.connection,
+            PORTAL_DESTINATION,
+            PORTAL_PATH,
+            WALLPAPER_INTERFACE,
+        )
+        .map_err(dbus_error)?;
+        portal
+            .call_method("SetWallpaperURI", &("", uri.as_str(), options))
+            .map_err(dbus_error)?;
+
+        // The signal iterator has no timeout, so it is drained on a thread left behind on expiry
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(responses.next());
+        });
+        let response = match receiver.recv_timeout(RESPONSE_TIMEOUT) {
+            Ok(Some(response)) => response,
+            Err(RecvTimeoutError::Timeout) => {
+                return Err(Error::DesktopEnv(format!(
+                    "Desktop portal did not answer the wallpaper request within {} seconds",
+                    RESPONSE_TIMEOUT.as_secs()
+                )));
+            }
+            Ok(None) | Err(RecvTimeoutError::Disconnected) => {
+                return Err(Error::DesktopEnv(
+                    "Desktop portal did not answer the wallpaper request".to_string(),
+                ));
+            }
+        };
+        let (status, _results): (u32, HashMap<String, OwnedValue>) =
+            response.body().deserialize().map_err(dbus_error)?;
+
+        match status {
+            0 => Ok(()),
+            1 => Err(Error::DesktopEnv(
+                "Setting the wallpaper was cancelled".to_string(),
+            )),
+            _ => Err(Error::DesktopEnv(
+                "Desktop portal failed to set the wallpaper".to_string(),
+            )),
+        }
+    }
+
+    fn get_wallpaper(&self, _screen: Option<&str>) -> Result<Option<PathBuf>> {
+        // The portal can only set wallpapers
+        Ok(None)
+    }
+
+    fn clear_wallpaper(&self) -> Result<()> {
+        Err(Error::DesktopEnv(
+            "The desktop portal can not clear the wallpaper".to_string(),
+        ))
+    }
+
+    fn notify(
+        &self,
+        title: &str,
+        message: &str,
+        image: Option<&Path>,
+        link: Option<&str>,
+    ) -> Result<()> {
+        #[cfg(not(any(feature = "cli", feature = "gui")))]
+        let _ = (title, message, image, link);
+        #[cfg(any(feature = "cli", feature = "gui"))]
+        {
+            send_notification(title, message, image, link)?;
+        }
+        Ok(())
+    }
+}
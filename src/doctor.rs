@@ -0,0 +1,108 @@
+use crate::apod::{api_key_from_env, stored_api_key};
+use crate::desktop::{Desktop, detect_desktop};
+use crate::utils::{check_save_folder_writable, command_exists};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    // Failed optional checks only disable a feature instead of breaking the tool
+    pub required: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &str, ok: bool, required: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            ok,
+            required,
+            detail: detail.into(),
+        }
+    }
+
+    fn command(command: &str, required: bool, purpose: &str) -> Self {
+        let ok = command_exists(command);
+        let detail = if ok {
+            format!("Found, {}", purpose)
+        } else {
+            format!("Not installed, needed for {}", purpose)
+        };
+        Self::new(command, ok, required, detail)
+    }
+}
+
+pub fn run_checks(save_folder: &Path, api_key: Option<&str>) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let desktop = detect_desktop();
+    checks.push(match desktop {
+        Some(desktop) => Check::new("desktop environment", true, true, desktop.name()),
+        None => Check::new(
+            "desktop environment",
+            false,
+            true,
+            format!(
+                "No supported desktop environment found (XDG_CURRENT_DESKTOP={})",
+                std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default()
+            ),
+        ),
+    });
+
+    match desktop {
+        Some(Desktop::Hyprland) => {
+            let tools = [
+                Check::command("hyprpaper", false, "setting wallpapers per monitor"),
+                Check::command("swww", false, "setting wallpapers with transitions"),
+                Check::command("swaybg", false, "setting a single wallpaper"),
+            ];
+            if !tools.iter().any(|check| check.ok) {
+                checks.push(Check::new(
+                    "wallpaper tool",
+                    false,
+                    true,
+                    "Install one of hyprpaper, swww or swaybg",
+                ));
+            }
+            checks.extend(tools);
+            checks.push(Check::command(
+                "gsettings",
+                false,
+                "following the system dark/light preference",
+            ));
+        }
+        Some(Desktop::Plasma | Desktop::Portal) | None => {}
+    }
+
+    checks.push(Check::command("exiftool", false, "storing image metadata"));
+    checks.push(Check::command("wal", false, "pywal color generation"));
+    checks.push(Check::command("wallust", false, "wallust color generation"));
+    checks.push(Check::command("matugen", false, "matugen color generation"));
+
+    let has_api_key =
+        api_key.is_some() || api_key_from_env().is_some() || stored_api_key().is_some();
+    checks.push(if has_api_key {
+        Check::new("NASA API key", true, false, "Set")
+    } else {
+        Check::new(
+            "NASA API key",
+            false,
+            false,
+            "Not set, DEMO_KEY only allows a few requests per hour. Run config set-key or set NASA_API_KEY (https://api.nasa.gov)",
+        )
+    });
+
+    checks.push(match check_save_folder_writable(save_folder) {
+        Ok(()) => Check::new(
+            "save folder",
+            true,
+            true,
+            format!("{} is writable", save_folder.display()),
+        ),
+        Err(e) => Check::new("save folder", false, true, e.to_string()),
+    });
+
+    checks
+}
@@ -1,31 +1,38 @@
-use crate::desktop::get_wallpaper_manager;
+use crate::config::{ColorBackend, THEMES, ThumbnailFormat, folder_name, resolve_theme};
+use crate::desktop::get_wallpaper_manager_with;
+use crate::history::previous_wallpaper;
 use crate::utils::{
-    generate_pywal_colors, generate_wallust_colors, get_cache_dir, get_image_files,
-    get_nasa_svg_path,
+    THUMBNAIL_SIZE, after_wallpaper_restored, after_wallpaper_set, apod_page_url, average_color,
+    get_image_files, get_nasa_svg_path, notification_body, run_color_backends, save_thumbnail,
+    thumbnail_color, thumbnail_path,
 };
+use iced::futures::SinkExt;
 use iced::{
-    keyboard::{key::Named, Key}, widget::{button, column, container, image, mouse_area, scrollable, stack, text}, Background, Border, Color, Element, Length, Padding, Pixels, Size,
-    Task,
-    Theme,
+    Background, Border, Color, Element, Length, Padding, Pixels, Size, Task, Theme,
+    keyboard::{Key, key::Named},
+    widget::{
+        Space, button, checkbox, column, container, image, mouse_area, row, scrollable, stack,
+        text, text_input,
+    },
 };
 use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 
 macro_rules! themes {
     ($($variant:ident),*) => {
-        fn get_available_themes() -> Vec<String> {
-            vec![$(stringify!($variant).to_string()),*]
-        }
-
-        fn string_to_theme(theme_str: &str) -> Theme {
+        fn string_to_theme(theme_str: &str) -> Option<Theme> {
             match theme_str {
-                $(stringify!($variant) => Theme::$variant,)*
-                _ => Theme::Dark,
+                $(stringify!($variant) => Some(Theme::$variant),)*
+                _ => None,
             }
         }
     };
 }
 
+// Keep in sync with config::THEMES
 themes!(
     Dark,
     Light,
@@ -50,43 +57,144 @@ themes!(
     Oxocarbon
 );
 
+const IMAGE_WIDTH: usize = 200;
+const IMAGE_HEIGHT: usize = 150;
+// Thumbnails are 400px wide, larger cells would show them upscaled
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 2.0;
+const ZOOM_STEP: f32 = 0.25;
+const GRID_SPACING: usize = 10;
+const DEFAULT_WINDOW_WIDTH: f64 = 870.0;
+const DEFAULT_WINDOW_HEIGHT: f64 = 800.0;
+const MIN_UI_SCALE: f64 = 0.5;
+const MAX_UI_SCALE: f64 = 4.0;
+const GRID_PADDING: f32 = 20.0;
+
 #[derive(Debug, Clone)]
 pub enum Message {
     ImageSelected(PathBuf),
+    WallpaperSet(PathBuf, Result<(), String>),
+    OpenWebpage(PathBuf),
     ImageHovered(usize),
     ImageUnhovered,
     LoadImages,
+    ReloadConfig,
     ImagesLoaded(Vec<(PathBuf, Option<PathBuf>)>),
     ThumbnailReady(PathBuf, Option<PathBuf>),
+    PlaceholderColorReady(PathBuf, [u8; 3]),
+    GridScrolled(scrollable::Viewport),
     ThemeChanged(String),
+    ThemePreview(String),
+    ThemePreviewEnded,
     ToggleThemeSelector,
+    ToggleSettings,
+    PywalToggled(bool),
+    WallustToggled(bool),
+    MultiMonitorToggled(bool),
+    RandomToggled(bool),
+    SaveFolderInput(String),
+    SaveFolderSubmitted,
+    OpenFolder,
+    FolderPicked(Option<PathBuf>),
+    CloseCropPreview,
     KeyPressed(Key),
 }
 
+struct CropPreview {
+    path: PathBuf,
+    image_size: (u32, u32),
+    // Unknown when the desktop does not report screen sizes
+    screen_aspect: Option<f64>,
+}
+
 pub struct WallpaperSwitcher {
     images: Vec<(PathBuf, Option<PathBuf>)>,
     images_per_row: Cell<usize>,
+    visible_rows: Cell<usize>,
+    placeholder_colors: HashMap<PathBuf, Color>,
     save_folder: PathBuf,
     hovered_image: Option<usize>,
+    grid_scroll_id: scrollable::Id,
+    scroll_offset: f32,
+    viewport_height: Cell<f32>,
     config: crate::config::WallpaperConfig,
     available_themes: Vec<String>,
+    system_theme: String,
     show_theme_selector: bool,
+    // Theme under the cursor in the selector, shown without being saved
+    preview_theme: Option<String>,
+    show_settings: bool,
+    save_folder_input: String,
     show_top_bar: bool,
+    // Failed thumbnails stay None, so progress is counted separately
+    pending_thumbnails: usize,
+    total_thumbnails: usize,
+    // Still being generated, a reload must not start them a second time
+    thumbnails_in_flight: HashSet<PathBuf>,
+    crop_preview: Option<CropPreview>,
+    // Taken from the config unless overridden on the command line
+    thumbnail_format: ThumbnailFormat,
+    // Limits concurrent decodes, the blocking pool alone would start hundreds at once
+    decode_permits: Arc<Semaphore>,
+    // Set while the wallpaper tool runs in the background
+    setting_wallpaper: bool,
+    // The selection came from Backspace, so history records a step back
+    restoring_previous: bool,
+    error_message: Option<String>,
 }
 
 impl WallpaperSwitcher {
-    pub fn new(save_folder: PathBuf) -> (Self, Task<Message>) {
-        let config = crate::config::WallpaperConfig::load_or_default().unwrap_or_default();
+    pub fn new(
+        save_folder: PathBuf,
+        thumbnail_format: Option<ThumbnailFormat>,
+    ) -> (Self, Task<Message>) {
+        let mut config = crate::config::WallpaperConfig::load_or_default().unwrap_or_default();
+        if !config.theme.is_empty() {
+            match resolve_theme(&config.theme) {
+                Ok(theme) => config.theme = theme.to_string(),
+                Err(e) => eprintln!("Warning: {}, using Dark", e),
+            }
+        }
+        let prefers_dark = config.theme.is_empty().then(|| {
+            get_wallpaper_manager_with(config.desktop_options())
+                .ok()
+                .and_then(|manager| manager.prefers_dark())
+        });
+        let system_theme = match prefers_dark.flatten() {
+            Some(false) => "Light",
+            _ => "Dark",
+        };
+
+        let thumbnail_format = thumbnail_format.unwrap_or(config.thumbnail_format);
+        let decode_threads = config.thumbnail_thread_count();
 
         let app = Self {
             images: Vec::new(),
             images_per_row: Cell::new(1),
+            visible_rows: Cell::new(1),
+            placeholder_colors: HashMap::new(),
             save_folder: save_folder.clone(),
             hovered_image: None,
+            grid_scroll_id: scrollable::Id::unique(),
+            scroll_offset: 0.0,
+            viewport_height: Cell::new(0.0),
             config,
-            available_themes: get_available_themes(),
+            available_themes: THEMES.iter().map(|theme| theme.to_string()).collect(),
+            system_theme: system_theme.to_string(),
             show_theme_selector: false,
+            preview_theme: None,
+            show_settings: false,
+            save_folder_input: save_folder.to_string_lossy().to_string(),
             show_top_bar: false,
+            pending_thumbnails: 0,
+            thumbnails_in_flight: HashSet::new(),
+            total_thumbnails: 0,
+            crop_preview: None,
+            thumbnail_format,
+            decode_permits: Arc::new(Semaphore::new(decode_threads)),
+            setting_wallpaper: false,
+            restoring_previous: false,
+            error_message: None,
         };
 
         let task = Self::load_folder_task(save_folder);
@@ -95,45 +203,85 @@ impl WallpaperSwitcher {
 
     fn load_folder_task(folder: PathBuf) -> Task<Message> {
         Task::future(async move {
-            let result = match get_image_files(&folder).await {
+            match get_image_files(&folder).await {
                 Ok(images) => images.into_iter().rev().map(|path| (path, None)).collect(),
                 Err(e) => {
                     println!("Error loading images: {}", e);
                     Vec::new()
                 }
-            };
-
-            result
+            }
         })
         .map(Message::ImagesLoaded)
     }
 
-    fn generate_single_thumbnail(image_path: PathBuf) -> Task<Message> {
-        Task::future(async move {
-            let cache_dir = get_cache_dir().unwrap().join(PathBuf::from("thumbnails"));
-
-            let hash = {
-                use std::collections::hash_map::DefaultHasher;
-                use std::hash::{Hash, Hasher};
-                let mut hasher = DefaultHasher::new();
-                image_path.hash(&mut hasher);
-                hasher.finish()
-            };
-
-            let original_name = image_path.file_stem().unwrap_or_default().to_string_lossy();
-            let extension = image_path.extension().unwrap_or_default().to_string_lossy();
-            let thumbnail_name = format!("thumb_{}_{}.{}", original_name, hash, extension);
-            let thumbnail_path = cache_dir.join(thumbnail_name);
+    fn generate_single_thumbnail(
+        image_path: PathBuf,
+        format: ThumbnailFormat,
+        quality: u8,
+        decode_permits: Arc<Semaphore>,
+    ) -> Task<Message> {
+        Task::stream(iced::stream::channel(2, move |mut output| async move {
+            let thumbnail_path = thumbnail_path(&image_path, format).unwrap();
 
             if thumbnail_path.exists() {
-                return (image_path, Some(thumbnail_path));
+                let thumb_path = thumbnail_path.clone();
+                if let Ok(Some(color)) =
+                    tokio::task::spawn_blocking(move || thumbnail_color(&thumb_path)).await
+                {
+                    let _ = output
+                        .send(Message::PlaceholderColorReady(image_path.clone(), color))
+                        .await;
+                }
+                let _ = output
+                    .send(Message::ThumbnailReady(image_path, Some(thumbnail_path)))
+                    .await;
+                return;
             }
 
+            // Held until the thumbnail is saved
+            let Ok(_permit) = decode_permits.acquire_owned().await else {
+                let _ = output.send(Message::ThumbnailReady(image_path, None)).await;
+                return;
+            };
+            let img_path = image_path.clone();
+            let decoded = match tokio::task::spawn_blocking(move || {
+                ::image::open(&img_path).map(|img| {
+                    let color = average_color(&img);
+                    (img, color)
+                })
+            })
+            .await
+            {
+                Ok(Ok(decoded)) => Some(decoded),
+                Ok(Err(e)) => {
+                    println!(
+                        "Failed to generate thumbnail for {}: {}",
+                        image_path.display(),
+                        e
+                    );
+                    None
+                }
+                Err(e) => {
+                    println!("Thumbnail generation task failed: {}", e);
+                    None
+                }
+            };
+
+            let Some((img, color)) = decoded else {
+                let _ = output.send(Message::ThumbnailReady(image_path, None)).await;
+                return;
+            };
+
+            // Tints the loading tile while the thumbnail is encoded and saved
+            let _ = output
+                .send(Message::PlaceholderColorReady(image_path.clone(), color))
+                .await;
+
             let img_path = image_path.clone();
             let thumb_path = thumbnail_path.clone();
 
-            match tokio::task::spawn_blocking(move || {
-                match crate::utils::generate_thumbnail(&img_path, &thumb_path, 400) {
+            let result = match tokio::task::spawn_blocking(move || {
+                match save_thumbnail(&img, &thumb_path, THUMBNAIL_SIZE, format, quality) {
                     Ok(_) => Some(thumb_path),
                     Err(e) => {
                         println!(
@@ -147,69 +295,181 @@ impl WallpaperSwitcher {
             })
             .await
             {
-                Ok(result) => (image_path, result),
+                Ok(result) => result,
                 Err(e) => {
                     println!("Thumbnail generation task failed: {}", e);
-                    (image_path, None)
+                    None
                 }
-            }
-        })
-        .map(|(original, thumbnail)| Message::ThumbnailReady(original, thumbnail))
+            };
+
+            let _ = output
+                .send(Message::ThumbnailReady(image_path, result))
+                .await;
+        }))
     }
 
     fn do_update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::LoadImages => Self::load_folder_task(self.save_folder.clone()),
+            // The folder is kept, it may have been given on the command line
+            Message::ReloadConfig => {
+                let mut config = match crate::config::WallpaperConfig::load_or_default() {
+                    Ok(config) => config,
+                    Err(e) => {
+                        self.error_message = Some(format!("Failed to reload config: {}", e));
+                        return Task::none();
+                    }
+                };
+                if !config.theme.is_empty() {
+                    match resolve_theme(&config.theme) {
+                        Ok(theme) => config.theme = theme.to_string(),
+                        Err(e) => eprintln!("Warning: {}, using Dark", e),
+                    }
+                }
+                self.decode_permits = Arc::new(Semaphore::new(config.thumbnail_thread_count()));
+                self.config = config;
+                self.error_message = None;
+                Self::load_folder_task(self.save_folder.clone())
+            }
             Message::ImagesLoaded(images) => {
+                // Thumbnails that are already shown survive a reload
+                let mut known: HashMap<PathBuf, PathBuf> = self
+                    .images
+                    .drain(..)
+                    .filter_map(|(path, thumbnail)| Some((path, thumbnail?)))
+                    .collect();
+                let images = images
+                    .into_iter()
+                    .filter(|(path, _)| !self.config.is_blacklisted(path))
+                    .map(|(path, thumbnail)| {
+                        let thumbnail = thumbnail.or_else(|| known.remove(&path));
+                        (path, thumbnail)
+                    })
+                    .collect();
                 self.images = images;
+                // A reload may have removed the hovered image
+                self.hovered_image = self.hovered_image.filter(|&idx| idx < self.images.len());
+                self.pending_thumbnails = self
+                    .images
+                    .iter()
+                    .filter(|(_, thumbnail)| thumbnail.is_none())
+                    .count();
+                self.total_thumbnails = self.pending_thumbnails;
 
                 let thumbnail_tasks: Vec<Task<Message>> = self
                     .images
                     .iter()
-                    .map(|(path, thumbnail)| {
-                        if thumbnail.is_none() {
-                            Self::generate_single_thumbnail(path.clone())
-                        } else {
-                            Task::none()
-                        }
+                    .filter(|(path, thumbnail)| {
+                        thumbnail.is_none() && self.thumbnails_in_flight.insert(path.clone())
+                    })
+                    .map(|(path, _)| {
+                        Self::generate_single_thumbnail(
+                            path.clone(),
+                            self.thumbnail_format,
+                            self.config.thumbnail_quality,
+                            self.decode_permits.clone(),
+                        )
                     })
                     .collect();
 
                 Task::batch(thumbnail_tasks)
             }
             Message::ThumbnailReady(original_path, thumbnail_path) => {
-                for (path, thumbnail) in &mut self.images {
-                    if *path == original_path {
-                        *thumbnail = thumbnail_path;
-                        break;
-                    }
+                self.thumbnails_in_flight.remove(&original_path);
+                // Images removed by a reload are no longer counted as pending
+                if let Some((_, thumbnail)) = self
+                    .images
+                    .iter_mut()
+                    .find(|(path, thumbnail)| *path == original_path && thumbnail.is_none())
+                {
+                    *thumbnail = thumbnail_path;
+                    self.pending_thumbnails = self.pending_thumbnails.saturating_sub(1);
                 }
                 Task::none()
             }
+            Message::PlaceholderColorReady(path, [r, g, b]) => {
+                self.placeholder_colors
+                    .insert(path, Color::from_rgba8(r, g, b, 0.8));
+                Task::none()
+            }
             Message::ImageSelected(path) => {
+                if self.setting_wallpaper {
+                    return Task::none();
+                }
                 println!("Selected wallpaper: {}", path.display());
-                let manager = get_wallpaper_manager().unwrap();
-                manager.set_wallpaper(&path, None).unwrap();
+                self.setting_wallpaper = true;
+                self.error_message = None;
 
-                manager
-                    .notify(
-                        "APOD Wallpaper",
-                        "Wallpapers updated successfully",
-                        Some(&get_nasa_svg_path().unwrap()),
-                    )
-                    .unwrap();
-
-                if self.config.pywal || self.config.wallust {
-                    if self.config.pywal {
-                        generate_pywal_colors(&path).unwrap();
-                    }
-                    if self.config.wallust {
-                        generate_wallust_colors(&path).unwrap();
+                // A hanging wallpaper tool must not freeze the window
+                let options = self.config.desktop_options();
+                let body_mode = self.config.notification_body;
+                let timeout = std::time::Duration::from_secs(self.config.gui_timeout);
+                let set_path = path.clone();
+                let set_wallpaper = tokio::task::spawn_blocking(move || {
+                    let manager = get_wallpaper_manager_with(options)?;
+                    manager.set_wallpaper(&set_path, None)?;
+                    if let Err(e) = manager.persist_wallpaper(&set_path, None) {
+                        println!("Failed to persist wallpaper: {}", e);
                     }
+                    let body =
+                        notification_body(body_mode, &set_path, "Wallpapers updated successfully");
+                    manager.notify("APOD Wallpaper", &body, Some(&get_nasa_svg_path()?), None)
+                });
+                Task::perform(
+                    async move {
+                        match tokio::time::timeout(timeout, set_wallpaper).await {
+                            Ok(Ok(result)) => result.map_err(|e| e.to_string()),
+                            Ok(Err(e)) => Err(e.to_string()),
+                            Err(_) => Err(format!(
+                                "The wallpaper tool did not respond within {} seconds",
+                                timeout.as_secs()
+                            )),
+                        }
+                    },
+                    move |result| Message::WallpaperSet(path.clone(), result),
+                )
+            }
+            Message::WallpaperSet(_, Err(e)) => {
+                println!("Failed to set wallpaper: {}", e);
+                self.setting_wallpaper = false;
+                self.restoring_previous = false;
+                self.error_message = Some(format!("Failed to set wallpaper: {}", e));
+                Task::none()
+            }
+            Message::WallpaperSet(path, Ok(())) => {
+                let bookkeeping = if self.restoring_previous {
+                    after_wallpaper_restored(&path, None, &self.config)
+                } else {
+                    after_wallpaper_set(&path, None, &self.config)
+                };
+                if let Err(e) = bookkeeping {
+                    println!(
+                        "Failed to update lock screen or latest wallpaper link: {}",
+                        e
+                    );
+                }
+
+                if let Err(e) = run_color_backends(&path, &self.config.color_backends) {
+                    println!("Failed to generate colors: {}", e);
                 }
 
                 iced::exit()
             }
+            Message::GridScrolled(viewport) => {
+                self.scroll_offset = viewport.absolute_offset().y;
+                Task::none()
+            }
+            Message::OpenWebpage(path) => {
+                match apod_page_url(&path) {
+                    Some(url) => {
+                        if let Err(e) = std::process::Command::new("xdg-open").arg(&url).spawn() {
+                            println!("Failed to open {}: {}", url, e);
+                        }
+                    }
+                    None => println!("No APOD page known for {}", path.display()),
+                }
+                Task::none()
+            }
             Message::ImageHovered(index) => {
                 self.hovered_image = Some(index);
                 Task::none()
@@ -222,10 +482,81 @@ impl WallpaperSwitcher {
                 self.config.theme = theme_name;
                 let _ = self.config.save();
                 self.show_theme_selector = false;
+                self.preview_theme = None;
+                Task::none()
+            }
+            Message::ThemePreview(theme_name) => {
+                self.preview_theme = Some(theme_name);
+                Task::none()
+            }
+            Message::ThemePreviewEnded => {
+                self.preview_theme = None;
                 Task::none()
             }
             Message::ToggleThemeSelector => {
                 self.show_theme_selector = !self.show_theme_selector;
+                self.show_settings = false;
+                self.preview_theme = None;
+                Task::none()
+            }
+            Message::ToggleSettings => {
+                self.show_settings = !self.show_settings;
+                self.show_theme_selector = false;
+                self.save_folder_input = self.save_folder.to_string_lossy().to_string();
+                Task::none()
+            }
+            Message::PywalToggled(enabled) => {
+                self.config.set_color_backend(ColorBackend::Pywal, enabled);
+                let _ = self.config.save();
+                Task::none()
+            }
+            Message::WallustToggled(enabled) => {
+                self.config
+                    .set_color_backend(ColorBackend::Wallust, enabled);
+                let _ = self.config.save();
+                Task::none()
+            }
+            Message::MultiMonitorToggled(enabled) => {
+                self.config.multi_monitor = enabled;
+                let _ = self.config.save();
+                Task::none()
+            }
+            Message::RandomToggled(enabled) => {
+                self.config.random = enabled;
+                let _ = self.config.save();
+                Task::none()
+            }
+            Message::SaveFolderInput(folder) => {
+                self.save_folder_input = folder;
+                Task::none()
+            }
+            Message::SaveFolderSubmitted => {
+                let folder = PathBuf::from(self.save_folder_input.trim());
+                if folder.as_os_str().is_empty() || folder == self.save_folder {
+                    return Task::none();
+                }
+
+                self.config.save_folder = folder.clone();
+                let _ = self.config.save();
+                self.save_folder = folder;
+                self.images.clear();
+                self.hovered_image = None;
+                Self::load_folder_task(self.save_folder.clone())
+            }
+            Message::OpenFolder => Task::perform(
+                rfd::AsyncFileDialog::new()
+                    .set_title("Open wallpaper folder")
+                    .set_directory(&self.save_folder)
+                    .pick_folder(),
+                |folder| Message::FolderPicked(folder.map(|folder| folder.path().to_path_buf())),
+            ),
+            Message::FolderPicked(Some(folder)) => {
+                self.save_folder_input = folder.to_string_lossy().to_string();
+                self.do_update(Message::SaveFolderSubmitted)
+            }
+            Message::FolderPicked(None) => Task::none(),
+            Message::CloseCropPreview => {
+                self.crop_preview = None;
                 Task::none()
             }
             Message::KeyPressed(key) => {
@@ -233,7 +564,37 @@ impl WallpaperSwitcher {
                     self.show_top_bar = !self.show_top_bar;
                     if !self.show_top_bar {
                         self.show_theme_selector = false;
+                        self.preview_theme = None;
+                        self.show_settings = false;
+                    }
+                }
+
+                // Keys typed into the settings form should not move the selection
+                if self.show_settings {
+                    return Task::none();
+                }
+
+                if let Some(preview) = &self.crop_preview {
+                    match key {
+                        Key::Named(Named::Escape) => self.crop_preview = None,
+                        Key::Character(ref c) if c.as_str() == "p" => self.crop_preview = None,
+                        Key::Named(Named::Enter) => {
+                            let path = preview.path.clone();
+                            return self.do_update(Message::ImageSelected(path));
+                        }
+                        _ => {}
                     }
+                    return Task::none();
+                }
+
+                match key {
+                    Key::Character(ref c) if c.as_str() == "r" => {
+                        return self.do_update(Message::LoadImages);
+                    }
+                    Key::Character(ref c) if c.as_str() == "R" => {
+                        return self.do_update(Message::ReloadConfig);
+                    }
+                    _ => {}
                 }
 
                 let total = self.images.len();
@@ -243,17 +604,16 @@ impl WallpaperSwitcher {
 
                 let mut idx = self.hovered_image.unwrap_or(0);
                 let images_per_row = self.images_per_row.get();
+                let page_size = images_per_row * self.visible_rows.get();
 
                 match key {
                     Key::Named(Named::ArrowRight) => {
                         idx = (idx + 1) % total;
-                        self.hovered_image = Some(idx);
-                        return self.do_update(Message::ImageHovered(idx));
+                        return self.hover_with_keyboard(idx);
                     }
                     Key::Named(Named::ArrowLeft) => {
                         idx = if idx == 0 { total - 1 } else { idx - 1 };
-                        self.hovered_image = Some(idx);
-                        return self.do_update(Message::ImageHovered(idx));
+                        return self.hover_with_keyboard(idx);
                     }
                     Key::Named(Named::ArrowDown) => {
                         idx = if idx + images_per_row >= total {
@@ -261,23 +621,64 @@ impl WallpaperSwitcher {
                         } else {
                             idx + images_per_row
                         };
-                        self.hovered_image = Some(idx);
-                        return self.do_update(Message::ImageHovered(idx));
+                        return self.hover_with_keyboard(idx);
                     }
                     Key::Named(Named::ArrowUp) => {
-                        idx = if idx < images_per_row {
-                            0
-                        } else {
-                            idx - images_per_row
-                        };
-                        self.hovered_image = Some(idx);
-                        return self.do_update(Message::ImageHovered(idx));
+                        idx = idx.saturating_sub(images_per_row);
+                        return self.hover_with_keyboard(idx);
                     }
-                    Key::Named(Named::Enter) => {
+                    Key::Named(Named::Home) => {
+                        return self.hover_with_keyboard(0);
+                    }
+                    Key::Named(Named::End) => {
+                        return self.hover_with_keyboard(total - 1);
+                    }
+                    Key::Named(Named::PageDown) => {
+                        idx = (idx + page_size).min(total - 1);
+                        return self.hover_with_keyboard(idx);
+                    }
+                    Key::Named(Named::PageUp) => {
+                        idx = idx.saturating_sub(page_size);
+                        return self.hover_with_keyboard(idx);
+                    }
+                    Key::Named(Named::Backspace) => match previous_wallpaper() {
+                        Ok(Some(entry)) => {
+                            self.restoring_previous |= !self.setting_wallpaper;
+                            return self.do_update(Message::ImageSelected(entry.path));
+                        }
+                        Ok(None) => println!("No previous wallpaper found in history"),
+                        Err(e) => println!("Failed to read wallpaper history: {}", e),
+                    },
+                    Key::Character(ref c) if matches!(c.as_str(), "+" | "=") => {
+                        self.zoom(ZOOM_STEP);
+                    }
+                    Key::Character(ref c) if c.as_str() == "-" => {
+                        self.zoom(-ZOOM_STEP);
+                    }
+                    Key::Character(ref c) if c.as_str() == "o" => {
+                        if let Some(idx) = self.hovered_image
+                            && let Some((path, _)) = self.images.get(idx)
+                        {
+                            return self.do_update(Message::OpenWebpage(path.clone()));
+                        }
+                    }
+                    Key::Character(ref c) if c.as_str() == "p" => {
+                        if let Some(idx) = self.hovered_image
+                            && let Some((path, _)) = self.images.get(idx)
+                        {
+                            self.open_crop_preview(path.clone());
+                        }
+                    }
+                    Key::Character(ref c) if c.as_str() == "x" => {
                         if let Some(idx) = self.hovered_image {
-                            if let Some((path, _)) = self.images.get(idx) {
-                                return self.do_update(Message::ImageSelected(path.clone()));
-                            }
+                            self.blacklist_image(idx);
+                        }
+                    }
+                    Key::Named(Named::Enter) => {
+                        if let Some(idx) = self.hovered_image
+                            && let Some((path, _)) = self.images.get(idx)
+                        {
+                            return self.do_update(Message::ImageSelected(path.clone()));
                         }
                     }
                     _ => {}
@@ -287,7 +688,89 @@ impl WallpaperSwitcher {
         }
     }
 
-    fn create_responsive_view(&self, actual_width: usize) -> Element<'_, Message> {
+    fn cell_size(&self) -> (usize, usize) {
+        let zoom = self.config.grid_zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        (
+            (IMAGE_WIDTH as f32 * zoom) as usize,
+            (IMAGE_HEIGHT as f32 * zoom) as usize,
+        )
+    }
+
+    fn zoom(&mut self, step: f32) {
+        let zoom = (self.config.grid_zoom + step).clamp(MIN_ZOOM, MAX_ZOOM);
+        if zoom != self.config.grid_zoom {
+            self.config.grid_zoom = zoom;
+            let _ = self.config.save();
+        }
+    }
+
+    fn blacklist_image(&mut self, index: usize) {
+        let Some((path, _)) = self.images.get(index) else {
+            return;
+        };
+        if !self.config.blacklist_image(path) {
+            println!(
+                "Cannot blacklist {}, no date in the file name",
+                path.display()
+            );
+            return;
+        }
+        let _ = self.config.save();
+        self.images.remove(index);
+        self.hovered_image = None;
+    }
+
+    fn open_crop_preview(&mut self, path: PathBuf) {
+        let image_size = match ::image::image_dimensions(&path) {
+            Ok(size) => size,
+            Err(e) => {
+                println!("Failed to read image {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let screen_aspect = get_wallpaper_manager_with(self.config.desktop_options())
+            .ok()
+            .and_then(|manager| {
+                manager
+                    .get_screen_info()
+                    .first()
+                    .and_then(|screen| screen.aspect_ratio())
+            });
+        self.crop_preview = Some(CropPreview {
+            path,
+            image_size,
+            screen_aspect,
+        });
+    }
+
+    fn hover_with_keyboard(&mut self, index: usize) -> Task<Message> {
+        self.hovered_image = Some(index);
+
+        let (_, image_height) = self.cell_size();
+        let row = (index / self.images_per_row.get().max(1)) as f32;
+        let top = GRID_PADDING + row * (image_height + GRID_SPACING) as f32;
+        let bottom = top + image_height as f32;
+
+        let target = if top < self.scroll_offset {
+            (top - GRID_PADDING).max(0.0)
+        } else if bottom > self.scroll_offset + self.viewport_height.get() {
+            bottom + GRID_PADDING - self.viewport_height.get()
+        } else {
+            return Task::none();
+        };
+
+        self.scroll_offset = target;
+        scrollable::scroll_to(
+            self.grid_scroll_id.clone(),
+            scrollable::AbsoluteOffset { x: 0.0, y: target },
+        )
+    }
+
+    fn create_responsive_view(
+        &self,
+        actual_width: usize,
+        actual_height: usize,
+    ) -> Element<'_, Message> {
         if self.images.is_empty() {
             return container(text("Loading images..."))
                 .width(Length::Fill)
@@ -297,13 +780,15 @@ impl WallpaperSwitcher {
                 .into();
         }
 
-        let image_width = 200;
-        let image_height = 150;
-        let spacing = 10;
+        let (image_width, image_height) = self.cell_size();
+        let spacing = GRID_SPACING;
         let padding = 10;
 
         let images_per_row = ((actual_width - padding) / (image_width + spacing)).max(1);
         self.images_per_row.set(images_per_row);
+        self.viewport_height.set(actual_height as f32);
+        self.visible_rows
+            .set((actual_height.saturating_sub(40) / (image_height + spacing)).max(1));
 
         let mut rows = Vec::new();
 
@@ -325,6 +810,12 @@ impl WallpaperSwitcher {
                         .padding(3)
                         .style({
                             let hovered_image = self.hovered_image;
+                            // Tints the padding around the thumbnail
+                            let background = self
+                                .placeholder_colors
+                                .get(original_path)
+                                .copied()
+                                .unwrap_or(Color::from_rgba(0.3, 0.3, 0.3, 0.5));
                             move |_theme| container::Style {
                                 border: Border {
                                     width: if hovered_image == Some(global_index) {
@@ -339,9 +830,7 @@ impl WallpaperSwitcher {
                                     },
                                     radius: 5.0.into(),
                                 },
-                                background: Some(Background::Color(Color::from_rgba(
-                                    0.3, 0.3, 0.3, 0.5,
-                                ))),
+                                background: Some(Background::Color(background)),
                                 ..Default::default()
                             }
                         })
@@ -357,10 +846,13 @@ impl WallpaperSwitcher {
                     .height(image_height as u16)
                     .style({
                         let hovered_image = self.hovered_image;
+                        let placeholder_color = self
+                            .placeholder_colors
+                            .get(original_path)
+                            .copied()
+                            .unwrap_or(Color::from_rgba(0.3, 0.3, 0.3, 0.5));
                         move |_theme| container::Style {
-                            background: Some(Background::Color(Color::from_rgba(
-                                0.3, 0.3, 0.3, 0.5,
-                            ))),
+                            background: Some(Background::Color(placeholder_color)),
                             border: Border {
                                 width: if hovered_image == Some(global_index) {
                                     3.0
@@ -393,10 +885,14 @@ impl WallpaperSwitcher {
 
         let grid = iced::widget::column(rows)
             .spacing(Pixels(spacing as f32))
-            .padding(20)
+            .padding(GRID_PADDING)
             .width(Length::Shrink);
 
-        let scrollable_content = scrollable(grid).width(Length::Shrink).height(Length::Fill);
+        let scrollable_content = scrollable(grid)
+            .id(self.grid_scroll_id.clone())
+            .on_scroll(Message::GridScrolled)
+            .width(Length::Shrink)
+            .height(Length::Fill);
 
         container(scrollable_content)
             .width(Length::Fill)
@@ -406,23 +902,199 @@ impl WallpaperSwitcher {
     }
 
     fn theme(&self) -> Theme {
-        string_to_theme(&self.config.theme)
+        if let Some(preview_theme) = &self.preview_theme {
+            string_to_theme(preview_theme).unwrap_or(Theme::Dark)
+        } else if self.config.theme.is_empty() {
+            string_to_theme(&self.system_theme).unwrap_or(Theme::Dark)
+        } else {
+            string_to_theme(&self.config.theme).unwrap_or(Theme::Dark)
+        }
     }
 }
 
-pub fn run_wallpaper_switcher(save_folder: PathBuf) -> iced::Result {
+fn crop_preview_view(preview: &CropPreview, size: Size) -> Element<'_, Message> {
+    // Leave room for the hint below the image
+    let (image_width, image_height) = preview.image_size;
+    let scale = ((size.width - 40.0) / image_width.max(1) as f32)
+        .min((size.height - 80.0) / image_height.max(1) as f32)
+        .max(0.0);
+    let width = image_width as f32 * scale;
+    let height = image_height as f32 * scale;
+
+    let mut layers: Vec<Element<Message>> = vec![
+        image(preview.path.clone())
+            .width(Length::Fixed(width))
+            .height(Length::Fixed(height))
+            .into(),
+    ];
+    let hint = if let Some(aspect) = preview.screen_aspect {
+        // The wallpaper is scaled to cover the screen, so the visible part is centered
+        let aspect = aspect as f32;
+        let (crop_width, crop_height) = if width / height > aspect {
+            (height * aspect, height)
+        } else {
+            (width, width / aspect)
+        };
+        layers.push(
+            container(
+                container(Space::new(crop_width, crop_height)).style(|theme: &Theme| {
+                    container::Style {
+                        border: Border::default().width(3).color(theme.palette().primary),
+                        ..Default::default()
+                    }
+                }),
+            )
+            .padding(Padding {
+                top: (height - crop_height) / 2.0,
+                right: 0.0,
+                bottom: 0.0,
+                left: (width - crop_width) / 2.0,
+            })
+            .into(),
+        );
+        "Enter to set, p or Esc to close"
+    } else {
+        "Screen size unknown. Enter to set, p or Esc to close"
+    };
+
+    mouse_area(
+        container(
+            column([
+                stack(layers)
+                    .width(Length::Fixed(width))
+                    .height(Length::Fixed(height))
+                    .into(),
+                text(hint).color(Color::WHITE).into(),
+            ])
+            .spacing(10)
+            .align_x(iced::Alignment::Center),
+        )
+        .style(|_: &Theme| container::Style {
+            background: Some(Background::Color(Color {
+                a: 0.85,
+                ..Color::BLACK
+            })),
+            ..Default::default()
+        })
+        .center_x(Length::Fill)
+        .center_y(Length::Fill),
+    )
+    .on_press(Message::CloseCropPreview)
+    .into()
+}
+
+fn ui_scale(config: &crate::config::WallpaperConfig) -> f64 {
+    std::env::var("APOD_WALLPAPER_SCALE")
+        .ok()
+        .and_then(|scale| scale.parse().ok())
+        .or(config.gui_scale)
+        .unwrap_or(1.0)
+        .clamp(MIN_UI_SCALE, MAX_UI_SCALE)
+}
+
+fn window_size(config: &crate::config::WallpaperConfig, ui_scale: f64) -> Size {
+    let screen = get_wallpaper_manager_with(config.desktop_options())
+        .ok()
+        .and_then(|manager| {
+            manager
+                .get_screen_info()
+                .into_iter()
+                .find(|screen| screen.width > 0 && screen.height > 0)
+        });
+
+    match screen {
+        // Window sizes are logical, so the monitor scale is divided out first
+        Some(screen) => {
+            let scale = if screen.scale > 0.0 {
+                screen.scale
+            } else {
+                1.0
+            };
+            Size {
+                width: (screen.width as f64 / scale * 0.5) as f32,
+                height: (screen.height as f64 / scale * 0.75) as f32,
+            }
+        }
+        None => Size {
+            width: (DEFAULT_WINDOW_WIDTH * ui_scale) as f32,
+            height: (DEFAULT_WINDOW_HEIGHT * ui_scale) as f32,
+        },
+    }
+}
+
+pub fn run_wallpaper_switcher(
+    save_folder: PathBuf,
+    thumbnail_format: Option<ThumbnailFormat>,
+) -> iced::Result {
+    let config = crate::config::WallpaperConfig::load_or_default().unwrap_or_default();
+    let ui_scale = ui_scale(&config);
+
     iced::application("APOD Wallpaper Switcher", update, view)
         .theme(|app: &WallpaperSwitcher| app.theme())
         .subscription(subscription)
-        .window_size(Size {
-            width: 870.0,
-            height: 800.0,
-        })
-        .run_with(|| WallpaperSwitcher::new(save_folder))
+        .scale_factor(move |_| ui_scale)
+        .window_size(window_size(&config, ui_scale))
+        .run_with(move || WallpaperSwitcher::new(save_folder, thumbnail_format))
+}
+
+fn subscription(app: &WallpaperSwitcher) -> iced::Subscription<Message> {
+    iced::Subscription::batch([
+        iced::keyboard::on_key_press(|key, _modifiers| Some(Message::KeyPressed(key))),
+        iced::Subscription::run_with_id(
+            app.save_folder.clone(),
+            watch_folder(app.save_folder.clone()),
+        ),
+    ])
 }
 
-fn subscription(_app: &WallpaperSwitcher) -> iced::Subscription<Message> {
-    iced::keyboard::on_key_press(|key, _modifiers| Some(Message::KeyPressed(key)))
+// Bursts of changes, like a running download, are collected into a single reload
+const WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(750);
+
+fn is_image_change(event: &notify::Event) -> bool {
+    use notify::EventKind;
+    use notify::event::ModifyKind;
+
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+    ) && event.paths.iter().any(|path| {
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .is_some_and(|ext| matches!(ext.as_str(), "jpg" | "jpeg" | "png"))
+    })
+}
+
+fn watch_folder(folder: PathBuf) -> impl iced::futures::Stream<Item = Message> {
+    iced::stream::channel(1, move |mut output| async move {
+        use notify::Watcher;
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event
+                && is_image_change(&event)
+            {
+                let _ = sender.send(());
+            }
+        });
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("Warning: Failed to watch {}: {}", folder.display(), e);
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(&folder, notify::RecursiveMode::Recursive) {
+            eprintln!("Warning: Failed to watch {}: {}", folder.display(), e);
+            return;
+        }
+
+        while receiver.recv().await.is_some() {
+            while let Ok(Some(())) = tokio::time::timeout(WATCH_DEBOUNCE, receiver.recv()).await {}
+            if output.send(Message::LoadImages).await.is_err() {
+                break;
+            }
+        }
+    })
 }
 
 fn update(app: &mut WallpaperSwitcher, message: Message) -> Task<Message> {
@@ -430,18 +1102,23 @@ fn update(app: &mut WallpaperSwitcher, message: Message) -> Task<Message> {
 }
 
 fn view(app: &WallpaperSwitcher) -> Element<'_, Message> {
-    let main_content =
-        iced::widget::responsive(move |size| app.create_responsive_view(size.width as usize));
+    let main_content = iced::widget::responsive(move |size| {
+        app.create_responsive_view(size.width as usize, size.height as usize)
+    });
 
     if app.show_theme_selector {
         let theme_buttons: Vec<Element<Message>> = app
             .available_themes
             .iter()
             .map(|theme| {
-                button(text(theme))
-                    .on_press(Message::ThemeChanged(theme.clone()))
-                    .width(Length::Fill)
-                    .into()
+                mouse_area(
+                    button(text(theme))
+                        .on_press(Message::ThemeChanged(theme.clone()))
+                        .width(Length::Fill),
+                )
+                .on_enter(Message::ThemePreview(theme.clone()))
+                .on_exit(Message::ThemePreviewEnded)
+                .into()
             })
             .collect();
 
@@ -465,14 +1142,88 @@ fn view(app: &WallpaperSwitcher) -> Element<'_, Message> {
         .center_y(Length::Fill);
 
         stack([main_content.into(), theme_selector.into()]).into()
+    } else if app.show_settings {
+        let settings = container(
+            column([
+                text("Settings").size(20).into(),
+                checkbox("Generate pywal colors", app.config.pywal)
+                    .on_toggle(Message::PywalToggled)
+                    .into(),
+                checkbox("Generate wallust colors", app.config.wallust)
+                    .on_toggle(Message::WallustToggled)
+                    .into(),
+                checkbox("Set wallpaper on every monitor", app.config.multi_monitor)
+                    .on_toggle(Message::MultiMonitorToggled)
+                    .into(),
+                checkbox("Pick a random APOD", app.config.random)
+                    .on_toggle(Message::RandomToggled)
+                    .into(),
+                text("Save folder").into(),
+                row([
+                    text_input("Save folder", &app.save_folder_input)
+                        .on_input(Message::SaveFolderInput)
+                        .on_submit(Message::SaveFolderSubmitted)
+                        .into(),
+                    button("Apply")
+                        .on_press(Message::SaveFolderSubmitted)
+                        .into(),
+                ])
+                .spacing(5)
+                .into(),
+                row(app.config.folders.iter().map(|folder| {
+                    button(text(folder_name(folder)))
+                        .on_press(Message::FolderPicked(Some(folder.clone())))
+                        .into()
+                }))
+                .spacing(5)
+                .into(),
+                button("Close").on_press(Message::ToggleSettings).into(),
+            ])
+            .spacing(10)
+            .padding(20),
+        )
+        .style(|theme: &Theme| container::Style {
+            background: Some(Background::Color(theme.palette().background)),
+            border: Border::default().width(2).color(theme.palette().primary),
+            ..Default::default()
+        })
+        .center_x(Length::Fill)
+        .center_y(Length::Fill);
+
+        stack([main_content.into(), settings.into()]).into()
+    } else if let Some(preview) = &app.crop_preview {
+        let preview = iced::widget::responsive(move |size| crop_preview_view(preview, size));
+        stack([main_content.into(), preview.into()]).into()
     } else {
         let mut content = vec![main_content.into()];
 
-        if app.show_top_bar {
-            let theme_button = button("Theme").on_press(Message::ToggleThemeSelector);
+        let status = if let Some(error) = &app.error_message {
+            Some(text(error).style(text::danger))
+        } else if app.setting_wallpaper {
+            Some(text("Setting wallpaper..."))
+        } else {
+            (app.pending_thumbnails > 0).then(|| {
+                text(format!(
+                    "Generating thumbnails {}/{}",
+                    app.total_thumbnails - app.pending_thumbnails,
+                    app.total_thumbnails
+                ))
+            })
+        };
+        if app.show_top_bar || status.is_some() {
+            let mut top_bar = row([]).spacing(5).align_y(iced::Alignment::Center);
+            if app.show_top_bar {
+                top_bar = top_bar
+                    .push(button("Theme").on_press(Message::ToggleThemeSelector))
+                    .push(button("Settings").on_press(Message::ToggleSettings))
+                    .push(button("Open folder").on_press(Message::OpenFolder));
+            }
+            if let Some(status) = status {
+                top_bar = top_bar.push(status);
+            }
             content.insert(
                 0,
-                container(theme_button)
+                container(top_bar)
                     .padding(Padding {
                         top: 10.0,
                         right: 0.0,
@@ -1,11 +1,15 @@
 pub mod image_grid;
 
+use crate::config::ThumbnailFormat;
 use std::path::PathBuf;
 
-pub fn run_switcher(save_folder: PathBuf) -> crate::Result<()> {
+pub fn run_switcher(
+    save_folder: PathBuf,
+    thumbnail_format: Option<ThumbnailFormat>,
+) -> crate::Result<()> {
     use iced::Result as IcedResult;
 
-    let result: IcedResult = image_grid::run_wallpaper_switcher(save_folder);
+    let result: IcedResult = image_grid::run_wallpaper_switcher(save_folder, thumbnail_format);
 
     result.map_err(|e| crate::Error::DesktopEnv(format!("GUI error: {e}")))?;
     Ok(())
@@ -0,0 +1,131 @@
+use crate::utils::{get_all_metadata, get_cache_dir};
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{OpenOptions, read_to_string};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: u64,
+    pub path: PathBuf,
+    pub screen: Option<String>,
+    pub title: Option<String>,
+    // Written by --previous, replaying it steps back instead of adding a wallpaper
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub restored: bool,
+}
+
+fn history_path() -> Result<PathBuf> {
+    Ok(get_cache_dir()?.join(PathBuf::from("history.jsonl")))
+}
+
+pub fn record_wallpaper(path: &Path, screen: Option<&str>, restored: bool) -> Result<()> {
+    let path = path.canonicalize().unwrap_or(path.to_path_buf());
+    let title = get_all_metadata(std::slice::from_ref(&path))
+        .ok()
+        .and_then(|mut metadata| metadata.remove(&path))
+        .and_then(|mut metadata| match metadata.remove("Title") {
+            Some(serde_json::Value::String(title)) => Some(title),
+            _ => None,
+        });
+
+    let entry = HistoryEntry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0),
+        path,
+        screen: screen.map(|screen| screen.to_string()),
+        title,
+        restored,
+    };
+
+    let mut line = serde_json::to_string(&entry).map_err(|e| Error::Config(e.to_string()))?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_path()?)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+pub fn read_history() -> Result<Vec<HistoryEntry>> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    // Skip lines that fail to parse so a single bad write doesn't hide the whole log
+    Ok(read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+pub fn previous_wallpaper() -> Result<Option<HistoryEntry>> {
+    Ok(find_previous(&read_history()?))
+}
+
+// Replays the log as a stack so repeated --previous keeps going back instead of toggling
+fn find_previous(history: &[HistoryEntry]) -> Option<HistoryEntry> {
+    let current = history.last()?;
+
+    let mut stack: Vec<&HistoryEntry> = Vec::new();
+    for entry in history
+        .iter()
+        .filter(|entry| entry.screen == current.screen)
+    {
+        if entry.restored {
+            stack.pop();
+        } else {
+            stack.push(entry);
+        }
+    }
+
+    stack
+        .iter()
+        .rev()
+        .find(|entry| entry.path != current.path && entry.path.exists())
+        .map(|entry| (*entry).clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &Path, restored: bool) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: 0,
+            path: path.to_path_buf(),
+            screen: None,
+            title: None,
+            restored,
+        }
+    }
+
+    #[test]
+    fn previous_keeps_going_back() {
+        let dir = std::env::temp_dir().join(format!("apod-history-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let [a, b, c] = ["a.jpg", "b.jpg", "c.jpg"].map(|name| {
+            let path = dir.join(name);
+            std::fs::write(&path, b"").unwrap();
+            path
+        });
+
+        let mut history = vec![entry(&a, false), entry(&b, false), entry(&c, false)];
+        assert_eq!(find_previous(&history).unwrap().path, b);
+
+        history.push(entry(&b, true));
+        assert_eq!(find_previous(&history).unwrap().path, a);
+
+        history.push(entry(&a, true));
+        assert!(find_previous(&history).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
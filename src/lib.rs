@@ -1,22 +1,36 @@
 pub mod desktop;
 pub mod utils;
 
-#[cfg(feature = "cli")]
+#[cfg(feature = "download-only")]
 pub mod apod;
-#[cfg(any(feature = "cli", feature = "gui"))]
+#[cfg(feature = "download-only")]
+pub mod archive;
+#[cfg(any(feature = "download-only", feature = "gui", feature = "tui"))]
 pub mod config;
+#[cfg(feature = "cli")]
+pub mod doctor;
 #[cfg(feature = "gui")]
 pub mod gui;
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
+pub mod history;
+#[cfg(feature = "cli")]
+pub mod metrics;
+#[cfg(feature = "cli")]
+pub mod schedule;
+#[cfg(feature = "download-only")]
+pub mod source;
+#[cfg(feature = "tui")]
+pub mod tui;
 
-#[cfg(any(feature = "cli"))]
+#[cfg(feature = "download-only")]
 pub use apod::ApodClient;
-#[cfg(any(feature = "cli", feature = "gui"))]
+#[cfg(any(feature = "download-only", feature = "gui", feature = "tui"))]
 pub use config::WallpaperConfig;
 pub use desktop::WallpaperManager;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[cfg(feature = "cli")]
+    #[cfg(feature = "download-only")]
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
@@ -29,7 +43,7 @@ pub enum Error {
     #[error("API error: {0}")]
     Api(String),
 
-    #[cfg(feature = "gui")]
+    #[cfg(any(feature = "download-only", feature = "gui"))]
     #[error("Image processing error: {0}")]
     Image(#[from] image::ImageError),
 }
@@ -1,10 +1,37 @@
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use apod_wallpaper::{
-    desktop::get_wallpaper_manager, utils::{generate_pywal_colors, generate_wallust_colors, get_nasa_svg_path},
-    ApodClient,
-    WallpaperConfig,
+    ApodClient, WallpaperConfig,
+    apod::{
+        ApodClientBuilder, DateFilter, DownloadProgress, DownloadStatus, read_date_list,
+        store_api_key,
+    },
+    archive::{ExportFormat, export_archive, verify_archive},
+    config::{THEMES, folder_name},
+    utils::{
+        acquire_lock, check_save_folder_writable, date_from_file_name, generate_thumbnails,
+        list_image_files, prune_archive, remove_image, thumbnail_path,
+    },
+};
+
+#[cfg(feature = "cli")]
+use apod_wallpaper::{
+    config::{ColorBackend, OnNoImage},
+    desktop::{Screen, get_wallpaper_manager_with, hyprland::kill_spawned_children},
+    doctor::run_checks,
+    history::{previous_wallpaper, read_history, record_wallpaper},
+    metrics::DaemonMetrics,
+    schedule::Schedule,
+    source::sources_from_names,
+    utils::{
+        after_wallpaper_restored, after_wallpaper_set, apod_page_url, assign_images_to_screens,
+        get_all_metadata, get_nasa_svg_path, image_date, notification_body, run_color_backends,
+        shutdown_signal,
+    },
 };
 
 #[derive(Parser)]
@@ -23,34 +50,127 @@ pub struct Args {
         help = "Folder to save wallpapers to (will be saved in config if used once)"
     )]
     folder: Option<PathBuf>,
+    #[arg(
+        long,
+        conflicts_with = "folder",
+        help = "Use one of the folders configured in folders by its name"
+    )]
+    folder_name: Option<String>,
     #[arg(
         short,
         long,
         help = "Will use options from config file (can be overridden)"
     )]
     use_config: bool,
+    #[cfg(feature = "cli")]
     #[arg(
         short,
         long,
         help = "Enable multi-monitor support (different wallpaper for each monitor)"
     )]
     multi_monitor: bool,
+    #[cfg(feature = "cli")]
     #[arg(
         short,
         long,
         help = "Fetch a random wallpaper from all available in the Folder"
     )]
     random: bool,
+    #[cfg(feature = "cli")]
     #[arg(
         long,
         help = "Generate pywal colors from the wallpaper (requires pywal to be installed)"
     )]
     pywal: bool,
+    #[cfg(feature = "cli")]
     #[arg(
         long,
         help = "Generate wallust colors from the wallpaper (requires wallust to be installed)"
     )]
     wallust: bool,
+    #[cfg(feature = "cli")]
+    #[arg(
+        long,
+        conflicts_with = "pywal",
+        help = "Skip pywal color generation for this run even if enabled in the config"
+    )]
+    no_pywal: bool,
+    #[cfg(feature = "cli")]
+    #[arg(
+        long,
+        conflicts_with = "wallust",
+        help = "Skip wallust color generation for this run even if enabled in the config"
+    )]
+    no_wallust: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Also keep the standard resolution image next to the HD one (saved as <date>-sd)"
+    )]
+    keep_both: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "NASA API key (overrides NASA_API_KEY_FILE, NASA_API_KEY and config set-key)"
+    )]
+    api_key: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Don't write EXIF metadata (title, description) to downloaded images"
+    )]
+    no_metadata: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Print a single JSON object with the result instead of human readable output"
+    )]
+    json: bool,
+    #[cfg(feature = "cli")]
+    #[arg(
+        long,
+        help = "Only use images already in the Folder, never download (works offline)"
+    )]
+    local_only: bool,
+    #[cfg(feature = "cli")]
+    #[arg(
+        long,
+        help = "Print the path of the selected image instead of setting it as wallpaper"
+    )]
+    print_path: bool,
+    #[cfg(feature = "cli")]
+    #[arg(
+        long,
+        conflicts_with_all = ["multi_monitor", "print_path"],
+        help = "Set a different wallpaper for every workspace (Hyprland, applied on switch by the workspaces subcommand)"
+    )]
+    per_workspace: bool,
+    #[cfg(feature = "cli")]
+    #[arg(
+        long,
+        help = "Only change the wallpaper until the wallpaper tool restarts (e.g. skip the hyprpaper.conf update)"
+    )]
+    session_only: bool,
+    #[cfg(feature = "cli")]
+    #[arg(
+        long,
+        conflicts_with_all = ["random", "local_only", "print_path"],
+        help = "Switch back to the previously set wallpaper"
+    )]
+    previous: bool,
+    #[cfg(feature = "cli")]
+    #[arg(
+        long,
+        help = "Print a one-line summary of what was changed at the end of the run"
+    )]
+    summary: bool,
+    #[cfg(feature = "cli")]
+    #[arg(
+        long,
+        value_enum,
+        help = "What to do when the newest APOD is not an image (overrides on_no_image in the config)"
+    )]
+    on_no_image: Option<OnNoImage>,
 }
 
 #[derive(Subcommand)]
@@ -63,14 +183,20 @@ enum Commands {
             help = "Folder to save wallpapers to (will be saved in config if used once)"
         )]
         folder: Option<PathBuf>,
-        #[arg(help = "Number of days to download (defaults to 7)", conflicts_with_all = ["date", "start_date"])]
+        #[arg(help = "Number of days to download (defaults to 7)", conflicts_with_all = ["date", "start_date", "from_file"])]
         days: Option<usize>,
         #[arg(
                     long,
                     help = "Download image for specific date (YYYY-MM-DD)",
-                    conflicts_with_all = ["days", "start_date"]
+                    conflicts_with_all = ["days", "start_date", "from_file"]
                 )]
         date: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "start_date",
+            help = "Download the dates listed in a file, one YYYY-MM-DD per line"
+        )]
+        from_file: Option<PathBuf>,
         #[arg(
             long,
             help = "Start date for date range (YYYY-MM-DD), requires --end-date",
@@ -83,12 +209,636 @@ enum Commands {
             requires = "start_date"
         )]
         end_date: Option<String>,
+        #[arg(long, help = "Re-download images even if they already exist locally")]
+        force: bool,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_parser = parse_weekday,
+            requires = "start_date",
+            help = "Comma separated weekdays to leave out of the date range (e.g. Sat,Sun)"
+        )]
+        skip_weekdays: Vec<chrono::Weekday>,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            requires = "start_date",
+            help = "Comma separated dates to leave out of the date range (YYYY-MM-DD)"
+        )]
+        skip_dates: Vec<chrono::NaiveDate>,
+        #[arg(
+            short,
+            long,
+            requires = "start_date",
+            help = "Allow date ranges longer than max_range_days"
+        )]
+        yes: bool,
+        #[cfg(feature = "cli")]
+        #[arg(
+            long,
+            help = "Set the most recent downloaded image as wallpaper afterwards"
+        )]
+        set_latest: bool,
     },
+    #[command(about = "Download recent APOD images whose title or explanation match keywords")]
+    Watch {
+        #[arg(
+            short,
+            long,
+            help = "Folder to save wallpapers to (will be saved in config if used once)"
+        )]
+        folder: Option<PathBuf>,
+        #[arg(
+            short,
+            long,
+            value_delimiter = ',',
+            required = true,
+            help = "Comma separated keywords to match (case-insensitive)"
+        )]
+        keywords: Vec<String>,
+        #[arg(long, default_value_t = 30, help = "Number of recent days to scan")]
+        days: usize,
+    },
+    #[command(about = "Show how many images already have a switcher thumbnail")]
+    Thumbnails {
+        #[arg(short, long, help = "Folder to read wallpapers from")]
+        folder: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Generate the missing thumbnails now instead of on the first switcher launch"
+        )]
+        generate: bool,
+    },
+    #[command(about = "Export metadata of all downloaded images")]
+    Export {
+        #[arg(short, long, help = "Folder to read wallpapers from")]
+        folder: Option<PathBuf>,
+        #[arg(long, value_enum, default_value = "json", help = "Output format")]
+        format: ExportFormat,
+        #[arg(
+            short,
+            long,
+            help = "File to write the export to (defaults to archive.<format> in the folder)"
+        )]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "Show the APOD entry without downloading anything")]
+    Info {
+        #[arg(long, help = "Date of the entry (YYYY-MM-DD), defaults to today")]
+        date: Option<chrono::NaiveDate>,
+    },
+    #[command(about = "Never pick the image of a date automatically again")]
+    Blacklist {
+        #[arg(help = "Date of the image (YYYY-MM-DD)")]
+        date: chrono::NaiveDate,
+        #[arg(long, help = "Also delete the downloaded images of this date")]
+        delete: bool,
+        #[arg(
+            long,
+            conflicts_with = "delete",
+            help = "Allow the date to be picked again"
+        )]
+        remove: bool,
+    },
+    #[command(about = "Find downloaded images that are corrupt or truncated")]
+    Verify {
+        #[arg(short, long, help = "Folder to check wallpapers in")]
+        folder: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Re-download corrupt images for the date in their file name"
+        )]
+        repair: bool,
+    },
+    #[command(about = "Change the config file")]
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    #[command(about = "List the themes available for the switcher")]
+    Themes,
+    #[command(about = "List the configured folders, select one with --folder-name")]
+    Folders,
+    #[cfg(feature = "cli")]
+    #[command(
+        about = "Keep running and switch to the wallpaper of each workspace when it is shown"
+    )]
+    Workspaces,
+    #[cfg(feature = "cli")]
+    #[command(about = "Clear the wallpaper set by apod-wallpaper")]
+    Reset,
+    #[cfg(feature = "cli")]
+    #[command(about = "Generate colors from the current wallpaper again without changing it")]
+    Recolor,
+    #[cfg(feature = "cli")]
+    #[command(about = "Keep a wallpaper until unpin, updates only download new images")]
+    Pin {
+        #[arg(help = "Image to set and pin (defaults to the current wallpaper)")]
+        path: Option<PathBuf>,
+    },
+    #[cfg(feature = "cli")]
+    #[command(about = "Let updates replace the wallpaper again")]
+    Unpin,
+    #[cfg(feature = "cli")]
+    #[command(about = "Check that all tools needed by apod-wallpaper are installed")]
+    Doctor,
+    #[cfg(feature = "cli")]
+    #[command(about = "Keep running and update the wallpaper on a schedule")]
+    Daemon {
+        #[arg(
+            long,
+            default_value_t = 24,
+            help = "Hours between updates when no refresh_time is configured"
+        )]
+        interval: i64,
+        #[arg(
+            long,
+            help = "Write node_exporter textfile metrics to this file after each update"
+        )]
+        metrics_file: Option<PathBuf>,
+    },
+    #[cfg(feature = "cli")]
+    #[command(about = "Show previously set wallpapers")]
+    History {
+        #[arg(short = 'n', long, help = "Only show the most recent entries")]
+        limit: Option<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    #[command(about = "Set a config value, theme names may be partial (e.g. mocha)")]
+    Set {
+        #[arg(help = "Config key, e.g. theme or prefetch_days")]
+        key: String,
+        #[arg(help = "New value, given as JSON or a plain string")]
+        value: String,
+    },
+    #[command(about = "Store the NASA API key, it is read without echo instead of as an argument")]
+    SetKey,
+}
+
+fn parse_weekday(value: &str) -> Result<chrono::Weekday, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a weekday like Mon or Sunday, got '{}'", value))
+}
+
+fn apod_client(args: &Args, config: &WallpaperConfig) -> ApodClientBuilder {
+    ApodClient::builder()
+        .api_key(args.api_key.clone())
+        .api_base(config.api_base.clone())
+        .keep_both(args.keep_both)
+        .keep_gif(config.keep_gif)
+        .prefer_hd(config.prefer_hd)
+        .folder_structure(config.folder_structure)
+        .skip_metadata(args.no_metadata || config.skip_metadata)
+        .blacklist(config.blacklisted_dates())
+        .explanation_language(config.explanation_language.clone())
+        .explanation_mirror(config.explanation_mirror.clone())
+}
+
+// Returns None when a pinned wallpaper has to be kept
+#[cfg(feature = "cli")]
+fn set_latest_wallpaper(
+    config: &WallpaperConfig,
+    client: &ApodClient,
+    folder: &Path,
+    session_only: bool,
+) -> anyhow::Result<Option<PathBuf>> {
+    if let Some(pinned) = &config.pinned_wallpaper {
+        eprintln!(
+            "Keeping pinned wallpaper {}, run unpin to allow updates",
+            pinned.display()
+        );
+        return Ok(None);
+    }
+    let Some(path) = client.get_latest_local_image(folder)? else {
+        anyhow::bail!("No images found in {}", folder.display());
+    };
+
+    let manager = get_wallpaper_manager_with(config.desktop_options())?;
+    manager.set_wallpaper(&path, None)?;
+    if !session_only {
+        manager.persist_wallpaper(&path, None)?;
+    }
+    after_wallpaper_set(&path, None, config)?;
+    Ok(Some(path))
+}
+
+#[cfg(feature = "cli")]
+fn run_summary(
+    image_path: &Path,
+    targets: usize,
+    per_workspace: bool,
+    colors: &[&str],
+    folder: &Path,
+) -> String {
+    let mut metadata = get_all_metadata(&[image_path.to_path_buf()]).unwrap_or_default();
+    let title = metadata
+        .remove(image_path)
+        .and_then(|mut metadata| metadata.remove("Title"))
+        .and_then(|title| title.as_str().map(str::to_string));
+    let mut summary = match image_date(image_path) {
+        Some(date) => format!("Set {}", date.format("%Y-%m-%d")),
+        None => format!("Set {}", image_path.display()),
+    };
+    if let Some(title) = title {
+        summary.push_str(&format!(" '{}'", title));
+    }
+    let kind = if per_workspace { "workspace" } else { "screen" };
+    summary.push_str(&format!(
+        " on {} {}{}",
+        targets,
+        kind,
+        if targets == 1 { "" } else { "s" }
+    ));
+    if !colors.is_empty() {
+        summary.push_str(&format!(", generated {} colors", colors.join(" and ")));
+    }
+    if let Ok(images) = list_image_files(folder) {
+        summary.push_str(&format!(", archive now {} images", images.len()));
+    }
+    summary
+}
+
+// The configured color_backends only apply with use_config, --pywal and --wallust always do
+#[cfg(feature = "cli")]
+fn color_backends(args: &Args, config: &WallpaperConfig, use_config: bool) -> Vec<ColorBackend> {
+    let mut backends = if use_config {
+        config.color_backends.clone()
+    } else {
+        Vec::new()
+    };
+    for (enabled, backend) in [
+        (args.pywal, ColorBackend::Pywal),
+        (args.wallust, ColorBackend::Wallust),
+    ] {
+        if enabled && !backends.contains(&backend) {
+            backends.push(backend);
+        }
+    }
+    backends.retain(|backend| match backend {
+        ColorBackend::Pywal => !args.no_pywal,
+        ColorBackend::Wallust => !args.no_wallust,
+        ColorBackend::Matugen => true,
+    });
+    backends
+}
+
+#[cfg(feature = "cli")]
+fn generate_colors(
+    image_path: &Path,
+    backends: &[ColorBackend],
+) -> anyhow::Result<Vec<&'static str>> {
+    Ok(run_color_backends(image_path, backends)?
+        .iter()
+        .map(|backend| backend.name())
+        .collect())
+}
+
+// Pruning problems are only reported, the downloaded images are kept either way
+fn prune_downloads(config: &WallpaperConfig, folder: &Path, keep: &[PathBuf], json: bool) -> usize {
+    let mut keep = keep.to_vec();
+    keep.extend(config.pinned_wallpaper.clone());
+    match prune_archive(folder, config.prune_policy(), &keep) {
+        Ok(removed) => {
+            if !json && !removed.is_empty() {
+                eprintln!(
+                    "Removed {} old images to stay within the archive limit",
+                    removed.len()
+                );
+            }
+            removed.len()
+        }
+        Err(e) => {
+            eprintln!("Warning: Failed to prune archive: {}", e);
+            0
+        }
+    }
+}
+
+fn print_json(value: serde_json::Value) {
+    println!("{}", value);
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
+
+fn download_progress(json: bool) -> impl FnMut(&DownloadProgress) {
+    let bar = (!json && std::io::stdout().is_terminal()).then(|| {
+        let bar = ProgressBar::new(0);
+        bar.set_style(
+            ProgressStyle::with_template("{bar:40.cyan/blue} {pos}/{len} days, {msg}")
+                .unwrap_or_else(|_| ProgressStyle::default_bar()),
+        );
+        bar
+    });
+    let (mut downloaded, mut skipped, mut failed) = (0, 0, 0);
+
+    move |progress| {
+        let Some(bar) = &bar else {
+            // Failures go to stderr, so they are still shown in JSON mode
+            if !json || matches!(progress.status, DownloadStatus::Failed(_)) {
+                progress.print();
+            }
+            return;
+        };
+
+        match &progress.status {
+            DownloadStatus::Started => {}
+            DownloadStatus::Downloaded => downloaded += 1,
+            DownloadStatus::Skipped | DownloadStatus::NoImage => skipped += 1,
+            DownloadStatus::Failed(e) => {
+                failed += 1;
+                bar.println(format!(
+                    "Failed to download image for {}: {}",
+                    progress.date.format("%Y-%m-%d"),
+                    e
+                ));
+            }
+        }
+
+        bar.set_length(progress.total as u64);
+        bar.set_position(progress.completed as u64);
+        bar.set_message(format!(
+            "{} downloaded, {} skipped, {} failed",
+            downloaded, skipped, failed
+        ));
+        if progress.completed == progress.total {
+            bar.finish();
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+// Returns the number of downloaded bytes
+async fn update_wallpapers(args: &Args, config: &mut WallpaperConfig) -> anyhow::Result<u64> {
+    let save_folder = args.folder.clone().unwrap_or(config.save_folder.clone());
+    check_save_folder_writable(&save_folder)?;
+    let client = apod_client(args, config).build();
+    client.remove_partial_downloads(&save_folder)?;
+    let manager = (!args.print_path)
+        .then(|| get_wallpaper_manager_with(config.desktop_options()))
+        .transpose()?;
+
+    let multi_monitor = args.multi_monitor || (args.use_config && config.multi_monitor);
+    let screens = match &manager {
+        Some(manager) if args.per_workspace => {
+            let workspaces = manager.get_workspaces();
+            if workspaces.is_empty() {
+                anyhow::bail!(
+                    "Per-workspace wallpapers are not supported on this desktop environment"
+                );
+            }
+            workspaces
+                .iter()
+                .map(|workspace| Screen::named(workspace))
+                .collect()
+        }
+        Some(manager) if multi_monitor && manager.supports_per_screen() => {
+            manager.get_screen_info()
+        }
+        Some(_) if multi_monitor => {
+            eprintln!(
+                "Warning: multi-monitor is not supported on this desktop environment, applying the first image to all screens"
+            );
+            vec![Screen::named("default")]
+        }
+        _ => vec![Screen::named("default")],
+    };
+
+    let mut image_paths = Vec::new();
+    let max_offset = 365;
+    let mut offset = 0;
+
+    let random = args.random || (args.use_config && config.random);
+    let fallback_sources = sources_from_names(&config.fallback_sources, &apod_client(args, config));
+    let on_no_image = args.on_no_image.unwrap_or(config.on_no_image);
+
+    while image_paths.len() < screens.len() && offset < max_offset {
+        let image_path = if args.local_only {
+            client.get_local_image(&save_folder, random, Some(offset))?
+        } else {
+            match client.get_image(&save_folder, random, Some(offset)).await? {
+                Some(image_path) => Some(image_path),
+                None => match ApodClient::target_date(random, Some(offset)) {
+                    Some(date) if !fallback_sources.is_empty() && !client.is_blacklisted(date) => {
+                        client
+                            .fetch_from_sources(&fallback_sources, &save_folder, date)
+                            .await?
+                    }
+                    _ => None,
+                },
+            }
+        };
+
+        if let Some(image_path) = image_path {
+            image_paths.push(image_path);
+        } else if offset == 0 && !random {
+            match on_no_image {
+                OnNoImage::WalkBack => {}
+                OnNoImage::KeepCurrent => {
+                    if args.json {
+                        print_json(serde_json::json!({ "kept_current": true }));
+                    } else {
+                        // Not the result, so --print-path keeps an empty stdout
+                        eprintln!("No image is available for today, keeping the current wallpaper");
+                    }
+                    return Ok(client.downloaded_bytes());
+                }
+                OnNoImage::Random => {
+                    while image_paths.len() < screens.len() {
+                        match client.get_local_image(&save_folder, true, None)? {
+                            Some(image_path) => image_paths.push(image_path),
+                            None => break,
+                        }
+                    }
+                    if image_paths.is_empty() {
+                        anyhow::bail!(
+                            "No image is available for today and there are no local images in {}",
+                            save_folder.display()
+                        );
+                    }
+                    break;
+                }
+                OnNoImage::Fail => anyhow::bail!("No image is available for today"),
+            }
+        }
+        offset += 1;
+    }
+
+    if args.local_only && image_paths.is_empty() {
+        anyhow::bail!("No local images found in {}", save_folder.display());
+    }
+    prune_downloads(config, &save_folder, &image_paths, args.json);
+
+    if let Some(pinned) = &config.pinned_wallpaper
+        && manager.is_some()
+    {
+        if args.json {
+            print_json(serde_json::json!({ "pinned": pinned }));
+        } else {
+            eprintln!(
+                "Keeping pinned wallpaper {}, run unpin to allow updates",
+                pinned.display()
+            );
+        }
+        return Ok(client.downloaded_bytes());
+    }
+
+    let Some(manager) = manager else {
+        let image_paths: Vec<PathBuf> = image_paths
+            .iter()
+            .map(|path| path.canonicalize().unwrap_or(path.clone()))
+            .collect();
+        if args.json {
+            print_json(serde_json::json!({ "paths": image_paths }));
+        } else {
+            for image_path in &image_paths {
+                println!("{}", image_path.display());
+            }
+        }
+        return Ok(client.downloaded_bytes());
+    };
+
+    let assignments = assign_images_to_screens(&screens, &image_paths);
+    for (index, (screen, image_path)) in assignments.iter().enumerate() {
+        if args.per_workspace {
+            manager.set_wallpaper_for_workspace(image_path, &screen.name)?;
+            continue;
+        }
+        manager.set_wallpaper(image_path, Some(&screen.name))?;
+        if !args.session_only
+            && let Err(e) = manager.persist_wallpaper(image_path, Some(&screen.name))
+        {
+            eprintln!("Warning: Failed to persist wallpaper: {}", e);
+        }
+        // The first screen is recorded below together with the lock screen and link
+        if index > 0
+            && let Err(e) = record_wallpaper(image_path, Some(&screen.name), false)
+        {
+            eprintln!("Warning: Failed to record wallpaper history: {}", e);
+        }
+    }
+
+    if let Some((screen, image_path)) = assignments.first() {
+        let screen = (!args.per_workspace).then_some(screen.name.as_str());
+        after_wallpaper_set(image_path, screen, config)?;
+    }
+
+    let backends = color_backends(args, config, args.use_config);
+    let color_image = match &config.color_source_monitor {
+        Some(monitor) => {
+            let image = assignments
+                .iter()
+                .find(|(screen, _)| screen.name.eq_ignore_ascii_case(monitor))
+                .map(|(_, image_path)| *image_path);
+            if image.is_none() && multi_monitor && !backends.is_empty() {
+                eprintln!(
+                    "Warning: color_source_monitor {} is not connected, using the first image for colors",
+                    monitor
+                );
+            }
+            image.or(image_paths.first())
+        }
+        None => image_paths.first(),
+    };
+    let colors = match color_image {
+        Some(color_image) => generate_colors(color_image, &backends)?,
+        None => Vec::new(),
+    };
+
+    if !args.use_config {
+        config.save_folder = save_folder.clone();
+        config.multi_monitor = args.multi_monitor;
+        config.random = args.random;
+        config.set_color_backend(ColorBackend::Pywal, args.pywal);
+        config.set_color_backend(ColorBackend::Wallust, args.wallust);
+        config.save()?;
+    }
+
+    if args.json {
+        let mut metadata = get_all_metadata(&image_paths).unwrap_or_default();
+        let wallpapers: Vec<serde_json::Value> = assignments
+            .iter()
+            .map(|(screen, image_path)| {
+                let title = metadata
+                    .get_mut(*image_path)
+                    .and_then(|metadata| metadata.remove("Title"));
+                let date = image_date(image_path).map(|date| date.format("%Y-%m-%d").to_string());
+                serde_json::json!({
+                    "path": image_path,
+                    "screen": screen.name,
+                    "date": date,
+                    "title": title,
+                })
+            })
+            .collect();
+        print_json(serde_json::json!({
+            "wallpapers": wallpapers,
+            "colors": colors,
+        }));
+    }
+
+    let link = image_paths.first().and_then(|path| apod_page_url(path));
+    let fallback = "Multiple wallpapers updated successfully";
+    let body = match image_paths.first() {
+        Some(image_path) if image_paths.len() == 1 => {
+            notification_body(config.notification_body, image_path, fallback)
+        }
+        _ => fallback.to_string(),
+    };
+    manager.notify(
+        "APOD Wallpaper",
+        &body,
+        Some(&get_nasa_svg_path().unwrap()),
+        link.as_deref(),
+    )?;
+
+    if args.summary
+        && !args.json
+        && let Some(image_path) = image_paths.first()
+    {
+        println!(
+            "{}",
+            run_summary(
+                image_path,
+                assignments.len(),
+                args.per_workspace,
+                &colors,
+                &save_folder
+            )
+        );
+    }
+
+    Ok(client.downloaded_bytes())
 }
 
 fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let _run_lock = match args.command {
+        Some(
+            Commands::Export { .. }
+            | Commands::Thumbnails { .. }
+            | Commands::Config { .. }
+            | Commands::Themes
+            | Commands::Folders
+            | Commands::Info { .. },
+        ) => None,
+        #[cfg(feature = "cli")]
+        Some(Commands::History { .. } | Commands::Doctor) => None,
+        // Long running commands must not block normal runs, the daemon locks per update
+        #[cfg(feature = "cli")]
+        Some(Commands::Daemon { .. } | Commands::Workspaces) => None,
+        _ => Some(acquire_lock("run")?),
+    };
     let mut config = WallpaperConfig::load_or_default()?;
+    if let Some(name) = &args.folder_name {
+        args.folder = Some(config.folder_by_name(name)?);
+    }
+    let client_builder = apod_client(&args, &config);
 
     let rt = tokio::runtime::Runtime::new()?;
     rt.block_on(async {
@@ -97,31 +847,125 @@ fn main() -> anyhow::Result<()> {
                 days,
                 folder,
                 date,
+                from_file,
                 start_date,
                 end_date,
+                force,
+                skip_weekdays,
+                skip_dates,
+                yes,
+                #[cfg(feature = "cli")]
+                set_latest,
             }) => {
-                let save_folder =
-                    folder.unwrap_or_else(|| args.folder.unwrap_or(config.save_folder.clone()));
+                let save_folder = folder
+                    .unwrap_or_else(|| args.folder.clone().unwrap_or(config.save_folder.clone()));
+                check_save_folder_writable(&save_folder)?;
 
-                let client = ApodClient::new();
+                let client = client_builder
+                    .force(force)
+                    .max_range_days((!yes).then_some(config.max_range_days))
+                    .build();
+                client.remove_partial_downloads(&save_folder)?;
                 let downloaded_count = if let Some(date_str) = date {
                     client
-                        .download_specific_date(&save_folder, &date_str)
+                        .download_specific_date(
+                            &save_folder,
+                            &date_str,
+                            download_progress(args.json),
+                        )
+                        .await?
+                } else if let Some(from_file) = from_file {
+                    let dates = read_date_list(&from_file)?;
+                    client
+                        .download_dates(&save_folder, &dates, download_progress(args.json))
                         .await?
                 } else if let (Some(start), Some(end)) = (start_date, end_date) {
                     client
-                        .download_date_range(&save_folder, &start, &end)
+                        .download_date_range(
+                            &save_folder,
+                            &start,
+                            &end,
+                            &DateFilter {
+                                skip_weekdays,
+                                skip_dates,
+                            },
+                            download_progress(args.json),
+                        )
                         .await?
                 } else {
                     let download_days = days.unwrap_or(7);
-                    client.download_range(&save_folder, download_days).await?
+                    client
+                        .download_range(&save_folder, download_days, download_progress(args.json))
+                        .await?
                 };
+                let pruned = prune_downloads(&config, &save_folder, &[], args.json);
 
-                println!(
-                    "Downloaded {} new APOD images to {}",
-                    downloaded_count,
-                    save_folder.display()
-                );
+                #[cfg(feature = "cli")]
+                let wallpaper = if set_latest {
+                    set_latest_wallpaper(&config, &client, &save_folder, args.session_only)?
+                } else {
+                    None
+                };
+                #[cfg(not(feature = "cli"))]
+                let wallpaper: Option<PathBuf> = None;
+
+                if args.json {
+                    print_json(serde_json::json!({
+                        "downloaded": downloaded_count,
+                        "bytes": client.downloaded_bytes(),
+                        "folder": save_folder,
+                        "pruned": pruned,
+                        "wallpaper": wallpaper,
+                    }));
+                } else {
+                    println!(
+                        "Downloaded {} new APOD images ({}) to {}",
+                        downloaded_count,
+                        format_size(client.downloaded_bytes()),
+                        save_folder.display()
+                    );
+                    if let Some(wallpaper) = &wallpaper {
+                        println!("Set wallpaper to {}", wallpaper.display());
+                    }
+                }
+
+                if !args.use_config {
+                    config.save_folder = save_folder;
+                    config.save()?;
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            Some(Commands::Watch {
+                folder,
+                keywords,
+                days,
+            }) => {
+                let save_folder = folder
+                    .unwrap_or_else(|| args.folder.clone().unwrap_or(config.save_folder.clone()));
+                check_save_folder_writable(&save_folder)?;
+
+                let client = client_builder.build();
+                client.remove_partial_downloads(&save_folder)?;
+                let downloaded_count = client
+                    .download_matching(&save_folder, days, &keywords, download_progress(args.json))
+                    .await?;
+
+                if args.json {
+                    print_json(serde_json::json!({
+                        "downloaded": downloaded_count,
+                        "bytes": client.downloaded_bytes(),
+                        "keywords": keywords,
+                        "folder": save_folder,
+                    }));
+                } else {
+                    println!(
+                        "Downloaded {} new APOD images ({}) matching {} to {}",
+                        downloaded_count,
+                        format_size(client.downloaded_bytes()),
+                        keywords.join(", "),
+                        save_folder.display()
+                    );
+                }
 
                 if !args.use_config {
                     config.save_folder = save_folder;
@@ -129,71 +973,556 @@ fn main() -> anyhow::Result<()> {
                 }
                 Ok::<(), anyhow::Error>(())
             }
-            None => {
-                let save_folder = args.folder.unwrap_or(config.save_folder);
-                let client = ApodClient::new();
-                let manager = get_wallpaper_manager()?;
+            Some(Commands::Thumbnails { folder, generate }) => {
+                let save_folder = folder
+                    .unwrap_or_else(|| args.folder.clone().unwrap_or(config.save_folder.clone()));
+                let images = list_image_files(&save_folder)?;
+                let missing: Vec<(PathBuf, PathBuf)> = images
+                    .iter()
+                    .filter_map(|image| {
+                        let thumbnail = thumbnail_path(image, config.thumbnail_format).ok()?;
+                        (!thumbnail.exists()).then(|| (image.clone(), thumbnail))
+                    })
+                    .collect();
+                let cached = images.len() - missing.len();
 
-                let screens = if args.multi_monitor || (args.use_config && config.multi_monitor) {
-                    manager.get_screens()
+                if !generate {
+                    if args.json {
+                        print_json(serde_json::json!({
+                            "images": images.len(),
+                            "cached": cached,
+                            "missing": missing.len(),
+                        }));
+                    } else {
+                        println!(
+                            "{} of {} images have a thumbnail, run with --generate to create the {} missing ones",
+                            cached,
+                            images.len(),
+                            missing.len()
+                        );
+                    }
+                    return Ok(());
+                }
+
+                let bar = if !args.json && std::io::stdout().is_terminal() {
+                    ProgressBar::new(missing.len() as u64)
                 } else {
-                    vec!["default".to_string()]
+                    ProgressBar::hidden()
                 };
+                bar.set_style(
+                    ProgressStyle::with_template("{bar:40.cyan/blue} {pos}/{len} thumbnails")
+                        .unwrap_or_else(|_| ProgressStyle::default_bar()),
+                );
+                let failed = AtomicUsize::new(0);
+                generate_thumbnails(
+                    &missing,
+                    config.thumbnail_format,
+                    config.thumbnail_quality,
+                    config.thumbnail_thread_count(),
+                    |image, result| {
+                        if let Err(e) = result {
+                            failed.fetch_add(1, Ordering::Relaxed);
+                            bar.suspend(|| {
+                                eprintln!(
+                                    "Failed to generate thumbnail for {}: {}",
+                                    image.display(),
+                                    e
+                                )
+                            });
+                        }
+                        bar.inc(1);
+                    },
+                );
+                bar.finish();
 
-                let mut image_paths = Vec::new();
-                let max_offset = 365;
-                let mut offset = 0;
+                let failed = failed.into_inner();
+                let generated = missing.len() - failed;
+                if args.json {
+                    print_json(serde_json::json!({
+                        "generated": generated,
+                        "failed": failed,
+                        "cached": cached,
+                    }));
+                } else {
+                    println!(
+                        "Generated {} thumbnails ({} already cached, {} failed)",
+                        generated, cached, failed
+                    );
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            Some(Commands::Export {
+                folder,
+                format,
+                output,
+            }) => {
+                let save_folder = folder
+                    .unwrap_or_else(|| args.folder.clone().unwrap_or(config.save_folder.clone()));
+                let output = output
+                    .unwrap_or_else(|| save_folder.join(format!("archive.{}", format.extension())));
 
-                while image_paths.len() < screens.len() && offset < max_offset {
-                    if let Some(image_path) = client
-                        .get_image(
-                            &save_folder,
-                            args.random || (args.use_config && config.random),
-                            Some(offset),
-                        )
-                        .await?
+                let exported_count = export_archive(&save_folder, format, &output)?;
+                if args.json {
+                    print_json(serde_json::json!({
+                        "exported": exported_count,
+                        "output": output,
+                    }));
+                } else {
+                    println!(
+                        "Exported metadata of {} images to {}",
+                        exported_count,
+                        output.display()
+                    );
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            Some(Commands::Blacklist {
+                date,
+                delete,
+                remove,
+            }) => {
+                let date_key = date.format("%Y-%m-%d").to_string();
+                if remove {
+                    config.blacklist.retain(|entry| *entry != date_key);
+                } else if !config.blacklist.contains(&date_key) {
+                    config.blacklist.push(date_key.clone());
+                }
+                config.save()?;
+
+                let mut deleted = Vec::new();
+                if delete {
+                    let save_folder = args.folder.as_ref().unwrap_or(&config.save_folder);
+                    for path in list_image_files(save_folder)? {
+                        if date_from_file_name(&path) == Some(date) {
+                            remove_image(&path)?;
+                            deleted.push(path);
+                        }
+                    }
+                }
+
+                if args.json {
+                    print_json(serde_json::json!({
+                        "date": date_key,
+                        "blacklisted": !remove,
+                        "deleted": deleted,
+                    }));
+                } else {
+                    if remove {
+                        println!("Removed {} from the blacklist", date);
+                    } else {
+                        println!("Blacklisted {}", date);
+                    }
+                    for path in &deleted {
+                        println!("Deleted {}", path.display());
+                    }
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            Some(Commands::Info { date }) => {
+                let client = client_builder.build();
+                let Some(apod) = client.fetch_metadata(date).await? else {
+                    anyhow::bail!("No APOD entry found");
+                };
+
+                if args.json {
+                    print_json(serde_json::to_value(&apod)?);
+                    return Ok(());
+                }
+                println!("{} ({})", apod.title, apod.date);
+                if let Some(copyright) = &apod.copyright {
+                    println!("Copyright: {}", copyright.trim());
+                }
+                println!("Media type: {}", apod.media_type);
+                if let Some(url) = apod.hd_url.as_ref().or(apod.url.as_ref()) {
+                    println!("URL: {}", url);
+                }
+                println!("\n{}", apod.explanation);
+                Ok::<(), anyhow::Error>(())
+            }
+            Some(Commands::Verify { folder, repair }) => {
+                let save_folder = folder
+                    .unwrap_or_else(|| args.folder.clone().unwrap_or(config.save_folder.clone()));
+                let (checked, corrupt) = verify_archive(&save_folder)?;
+
+                let client = client_builder.force(true).build();
+                let mut repaired = Vec::new();
+                for image in &corrupt {
+                    if !args.json {
+                        println!("Corrupt: {} ({})", image.path.display(), image.error);
+                    }
+                    if !repair {
+                        continue;
+                    }
+                    let Some(date) = &image.date else {
+                        eprintln!(
+                            "Cannot repair {}, no date in the file name",
+                            image.path.display()
+                        );
+                        continue;
+                    };
+
+                    // Removed first so the download is not skipped as unchanged by the server
+                    std::fs::remove_file(&image.path)?;
+                    match client
+                        .download_specific_date(&save_folder, date, download_progress(args.json))
+                        .await
                     {
-                        image_paths.push(image_path);
+                        Ok(count) if count > 0 => repaired.push(image.path.clone()),
+                        Ok(_) => eprintln!("No image available for {}", date),
+                        Err(e) => eprintln!("Failed to repair {}: {}", image.path.display(), e),
                     }
-                    offset += 1;
                 }
 
-                for (i, screen) in screens.iter().enumerate() {
-                    if i < image_paths.len() {
-                        manager.set_wallpaper(&image_paths[i], Some(screen))?;
+                if args.json {
+                    print_json(serde_json::json!({
+                        "checked": checked,
+                        "corrupt": corrupt,
+                        "repaired": repaired,
+                    }));
+                } else if repair {
+                    println!(
+                        "Checked {} images, {} corrupt, {} repaired",
+                        checked,
+                        corrupt.len(),
+                        repaired.len()
+                    );
+                } else {
+                    println!("Checked {} images, {} corrupt", checked, corrupt.len());
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            Some(Commands::Config {
+                action: ConfigAction::Set { key, value },
+            }) => {
+                config.set(&key, &value)?;
+                config.save()?;
+                let value = serde_json::to_value(&config)?[&key].clone();
+                if args.json {
+                    print_json(serde_json::json!({ &key: value }));
+                } else {
+                    println!("Set {} to {}", key, value);
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            Some(Commands::Config {
+                action: ConfigAction::SetKey,
+            }) => {
+                // Piped keys, e.g. from a password manager, are read from stdin
+                let key = if std::io::stdin().is_terminal() {
+                    rpassword::prompt_password("NASA API key: ")?
+                } else {
+                    let mut key = String::new();
+                    std::io::stdin().read_line(&mut key)?;
+                    key
+                };
+                if key.trim().is_empty() {
+                    anyhow::bail!("No API key entered, the stored key was left unchanged");
+                }
+                let path = store_api_key(&key)?;
+                if args.json {
+                    print_json(serde_json::json!({ "api_key_file": path }));
+                } else {
+                    println!("Stored the API key in {}", path.display());
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            Some(Commands::Themes) => {
+                if args.json {
+                    print_json(serde_json::json!(THEMES));
+                    return Ok(());
+                }
+                for theme in THEMES {
+                    let current = if *theme == config.theme {
+                        "  (current)"
+                    } else {
+                        ""
+                    };
+                    println!("{}{}", theme, current);
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            Some(Commands::Folders) => {
+                let active = args.folder.as_ref().unwrap_or(&config.save_folder);
+                if args.json {
+                    let folders: Vec<serde_json::Value> = config
+                        .folders
+                        .iter()
+                        .map(|folder| {
+                            serde_json::json!({
+                                "name": folder_name(folder),
+                                "path": folder,
+                                "active": folder == active,
+                            })
+                        })
+                        .collect();
+                    print_json(serde_json::json!(folders));
+                    return Ok(());
+                }
+                if config.folders.is_empty() {
+                    println!("No folders configured, add them to folders in the config");
+                }
+                for folder in &config.folders {
+                    let current = if folder == active { "  (active)" } else { "" };
+                    println!("{}  {}{}", folder_name(folder), folder.display(), current);
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            #[cfg(feature = "cli")]
+            Some(Commands::Workspaces) => {
+                let manager = get_wallpaper_manager_with(config.desktop_options())?;
+                manager.follow_workspaces()?;
+                Ok::<(), anyhow::Error>(())
+            }
+            #[cfg(feature = "cli")]
+            Some(Commands::Reset) => {
+                let manager = get_wallpaper_manager_with(config.desktop_options())?;
+                manager.clear_wallpaper()?;
+                if args.json {
+                    print_json(serde_json::json!({ "cleared": true }));
+                } else {
+                    println!("Cleared wallpaper");
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            #[cfg(feature = "cli")]
+            Some(Commands::Pin { ref path }) => {
+                let manager = get_wallpaper_manager_with(config.desktop_options())?;
+                let path = match path {
+                    Some(path) => {
+                        let path = path.canonicalize()?;
+                        manager.set_wallpaper(&path, None)?;
+                        if !args.session_only {
+                            manager.persist_wallpaper(&path, None)?;
+                        }
+                        after_wallpaper_set(&path, None, &config)?;
+                        path
                     }
+                    None => manager.get_wallpaper(None)?.ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Could not find the current wallpaper, pass an image to pin"
+                        )
+                    })?,
+                };
+
+                config.pinned_wallpaper = Some(path.clone());
+                config.save()?;
+                if args.json {
+                    print_json(serde_json::json!({ "pinned": path }));
+                } else {
+                    println!("Pinned {}", path.display());
                 }
+                Ok::<(), anyhow::Error>(())
+            }
+            #[cfg(feature = "cli")]
+            Some(Commands::Recolor) => {
+                let manager = get_wallpaper_manager_with(config.desktop_options())?;
+                let path = manager
+                    .get_wallpaper(None)?
+                    .ok_or_else(|| anyhow::anyhow!("Could not find the current wallpaper"))?;
+                let backends = color_backends(&args, &config, true);
+                let colors = generate_colors(&path, &backends)?;
+                if args.json {
+                    print_json(serde_json::json!({ "wallpaper": path, "colors": colors }));
+                } else if colors.is_empty() {
+                    println!(
+                        "No color backend is enabled, enable pywal or wallust or set color_backends"
+                    );
+                } else {
+                    println!(
+                        "Generated {} colors from {}",
+                        colors.join(" and "),
+                        path.display()
+                    );
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            #[cfg(feature = "cli")]
+            Some(Commands::Unpin) => {
+                let pinned = config.pinned_wallpaper.take();
+                config.save()?;
+                if args.json {
+                    print_json(serde_json::json!({ "unpinned": pinned }));
+                } else {
+                    match pinned {
+                        Some(path) => println!("Unpinned {}", path.display()),
+                        None => println!("No wallpaper was pinned"),
+                    }
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            #[cfg(feature = "cli")]
+            Some(Commands::Doctor) => {
+                let save_folder = args.folder.unwrap_or(config.save_folder.clone());
+                let checks = run_checks(&save_folder, args.api_key.as_deref());
+                let failed = checks
+                    .iter()
+                    .filter(|check| check.required && !check.ok)
+                    .count();
 
-                manager.notify(
-                    "APOD Wallpaper",
-                    "Multiple wallpapers updated successfully",
-                    Some(&get_nasa_svg_path().unwrap()),
+                if args.json {
+                    print_json(serde_json::json!(checks));
+                } else {
+                    let color = std::io::stdout().is_terminal();
+                    for check in &checks {
+                        let (mark, code) = match (check.ok, check.required) {
+                            (true, _) => ("ok", "32"),
+                            (false, true) => ("error", "31"),
+                            (false, false) => ("warning", "33"),
+                        };
+                        let mark = if color {
+                            format!("\x1b[{}m{:>7}\x1b[0m", code, mark)
+                        } else {
+                            format!("{:>7}", mark)
+                        };
+                        println!("{}  {}: {}", mark, check.name, check.detail);
+                    }
+                }
+
+                if failed > 0 {
+                    anyhow::bail!("{} required checks failed", failed);
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            #[cfg(feature = "cli")]
+            Some(Commands::Daemon {
+                interval,
+                ref metrics_file,
+            }) => {
+                let mut metrics = DaemonMetrics::default();
+                let schedule = Schedule::new(
+                    config.refresh_time.as_deref(),
+                    config.quiet_hours.as_deref(),
+                    chrono::TimeDelta::hours(interval),
                 )?;
 
-                if !image_paths.is_empty()
-                    && (args.pywal
-                        || args.wallust
-                        || (args.use_config && (config.pywal || config.wallust)))
-                {
-                    if args.pywal || (args.use_config && config.pywal) {
-                        generate_pywal_colors(&image_paths[0])?;
-                    }
-                    if args.wallust || (args.use_config && config.wallust) {
-                        generate_wallust_colors(&image_paths[0])?;
+                // Fills the folder once on startup, later runs only fetch the newest image
+                if !args.local_only && config.prefetch_days > 0 {
+                    let _run_lock = acquire_lock("run")?;
+                    let save_folder = args.folder.clone().unwrap_or(config.save_folder.clone());
+                    let client = client_builder.clone().build();
+                    if let Err(e) = client
+                        .download_range(
+                            &save_folder,
+                            config.prefetch_days,
+                            download_progress(args.json),
+                        )
+                        .await
+                    {
+                        eprintln!("Failed to prefetch recent images: {}", e);
                     }
                 }
 
-                if !args.use_config {
-                    config.save_folder = save_folder;
-                    config.multi_monitor = args.multi_monitor;
-                    config.random = args.random;
-                    config.pywal = args.pywal;
-                    config.wallust = args.wallust;
-                    config.save()?;
+                let daemon = async {
+                    loop {
+                        if schedule.is_quiet(chrono::Local::now().time()) {
+                            println!("Skipping wallpaper update during quiet hours");
+                        } else {
+                            let _run_lock = acquire_lock("run")?;
+                            // Pick up config changes made while the daemon was sleeping
+                            let mut config = WallpaperConfig::load_or_default()?;
+                            let result = update_wallpapers(&args, &mut config).await;
+                            if let Err(e) = &result {
+                                eprintln!("Failed to update wallpaper: {}", e);
+                            }
+
+                            if let Some(metrics_file) = metrics_file {
+                                let save_folder =
+                                    args.folder.as_ref().unwrap_or(&config.save_folder);
+                                let images =
+                                    list_image_files(save_folder).map_or(0, |images| images.len());
+                                metrics.record_run(
+                                    result.is_ok(),
+                                    *result.as_ref().unwrap_or(&0),
+                                    images,
+                                );
+                                if let Err(e) = metrics.write(metrics_file) {
+                                    eprintln!("Failed to write metrics: {}", e);
+                                }
+                            }
+                        }
+
+                        let next_change = schedule.next_change(chrono::Local::now());
+                        println!(
+                            "Next wallpaper update at {}",
+                            next_change.format("%Y-%m-%d %H:%M")
+                        );
+                        // Sleep in short steps so a suspended system does not delay the update
+                        while chrono::Local::now() < next_change {
+                            let remaining = (next_change - chrono::Local::now())
+                                .to_std()
+                                .unwrap_or_default();
+                            tokio::time::sleep(remaining.min(std::time::Duration::from_secs(60)))
+                                .await;
+                        }
+                    }
+                };
+
+                // The metrics file and history are written after each run, so stopping
+                // between runs or mid download leaves nothing to flush
+                tokio::select! {
+                    result = daemon => result,
+                    result = shutdown_signal() => {
+                        result?;
+                        println!("Received shutdown signal, stopping daemon");
+                        kill_spawned_children();
+                        Ok(())
+                    }
+                }
+            }
+            #[cfg(feature = "cli")]
+            Some(Commands::History { limit }) => {
+                let history = read_history()?;
+                let skip = limit.map_or(0, |limit| history.len().saturating_sub(limit));
+                if args.json {
+                    print_json(serde_json::json!(history[skip..]));
+                    return Ok(());
                 }
+                for entry in history.iter().skip(skip) {
+                    let set_at = chrono::DateTime::from_timestamp(entry.timestamp as i64, 0)
+                        .map(|time| {
+                            time.with_timezone(&chrono::Local)
+                                .format("%Y-%m-%d %H:%M")
+                                .to_string()
+                        })
+                        .unwrap_or_default();
+                    println!(
+                        "{}  {}  {}  {}",
+                        set_at,
+                        entry.screen.as_deref().unwrap_or("-"),
+                        entry.path.display(),
+                        entry.title.as_deref().unwrap_or("")
+                    );
+                }
+                Ok::<(), anyhow::Error>(())
+            }
+            #[cfg(feature = "cli")]
+            None if args.previous => {
+                let Some(entry) = previous_wallpaper()? else {
+                    anyhow::bail!("No previous wallpaper found in history");
+                };
 
+                let manager = get_wallpaper_manager_with(config.desktop_options())?;
+                manager.set_wallpaper(&entry.path, entry.screen.as_deref())?;
+                if !args.session_only {
+                    manager.persist_wallpaper(&entry.path, entry.screen.as_deref())?;
+                }
+                after_wallpaper_restored(&entry.path, entry.screen.as_deref(), &config)?;
+                if args.json {
+                    print_json(serde_json::json!({
+                        "path": entry.path,
+                        "screen": entry.screen,
+                    }));
+                } else {
+                    println!("Restored wallpaper {}", entry.path.display());
+                }
                 Ok::<(), anyhow::Error>(())
             }
+            #[cfg(not(feature = "cli"))]
+            None => anyhow::bail!(
+                "This build can only download images, use the download, watch or export subcommands"
+            ),
+            #[cfg(feature = "cli")]
+            None => update_wallpapers(&args, &mut config).await.map(|_| ()),
         }
     })?;
 
@@ -0,0 +1,86 @@
+use crate::{Error, Result};
+use std::fs::{rename, write};
+use std::path::Path;
+
+// Written in the node_exporter textfile collector format
+#[derive(Debug, Default)]
+pub struct DaemonMetrics {
+    last_run: i64,
+    last_success: Option<i64>,
+    last_run_ok: bool,
+    images: usize,
+    downloaded_bytes: u64,
+}
+
+impl DaemonMetrics {
+    pub fn record_run(&mut self, ok: bool, downloaded_bytes: u64, images: usize) {
+        let now = chrono::Utc::now().timestamp();
+        self.last_run = now;
+        self.last_run_ok = ok;
+        if ok {
+            self.last_success = Some(now);
+        }
+        self.images = images;
+        self.downloaded_bytes += downloaded_bytes;
+    }
+
+    fn render(&self) -> String {
+        let metrics = [
+            (
+                "apod_wallpaper_last_run_timestamp_seconds",
+                "gauge",
+                "Unix time of the last wallpaper update attempt",
+                self.last_run.to_string(),
+            ),
+            (
+                "apod_wallpaper_last_success_timestamp_seconds",
+                "gauge",
+                "Unix time of the last successful wallpaper update, 0 if there was none",
+                self.last_success.unwrap_or(0).to_string(),
+            ),
+            (
+                "apod_wallpaper_last_run_success",
+                "gauge",
+                "Whether the last wallpaper update succeeded",
+                u8::from(self.last_run_ok).to_string(),
+            ),
+            (
+                "apod_wallpaper_images",
+                "gauge",
+                "Number of images in the save folder",
+                self.images.to_string(),
+            ),
+            (
+                "apod_wallpaper_downloaded_bytes_total",
+                "counter",
+                "Bytes downloaded since the daemon was started",
+                self.downloaded_bytes.to_string(),
+            ),
+        ];
+
+        let mut content = String::new();
+        for (name, kind, help, value) in metrics {
+            content.push_str(&format!(
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+            ));
+        }
+        content
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        // The collector may read at any time, so the file is replaced in one step
+        let temp_path = path.with_file_name(format!(
+            ".{}.tmp",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        write(&temp_path, self.render())
+            .and_then(|_| rename(&temp_path, path))
+            .map_err(|e| {
+                Error::Config(format!(
+                    "Could not write metrics to {}: {}",
+                    path.display(),
+                    e
+                ))
+            })
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{Error, Result};
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
+
+pub struct Schedule {
+    refresh_time: Option<NaiveTime>,
+    interval: TimeDelta,
+    quiet_hours: Option<(NaiveTime, NaiveTime)>,
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| Error::Config(format!("Invalid time '{}', use HH:MM", value)))
+}
+
+fn to_local(time: NaiveDateTime) -> DateTime<Local> {
+    // Times inside a DST gap do not exist locally, so they are taken as UTC instead
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&time))
+}
+
+impl Schedule {
+    pub fn new(
+        refresh_time: Option<&str>,
+        quiet_hours: Option<&str>,
+        interval: TimeDelta,
+    ) -> Result<Self> {
+        let refresh_time = refresh_time.map(parse_time).transpose()?;
+        let quiet_hours = quiet_hours
+            .map(|range| {
+                let (start, end) = range.split_once('-').ok_or_else(|| {
+                    Error::Config(format!("Invalid quiet hours '{}', use HH:MM-HH:MM", range))
+                })?;
+                Ok::<_, Error>((parse_time(start)?, parse_time(end)?))
+            })
+            .transpose()?;
+
+        if refresh_time.is_none() && interval <= TimeDelta::zero() {
+            return Err(Error::Config(
+                "The refresh interval must be greater than zero".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            refresh_time,
+            interval,
+            quiet_hours,
+        })
+    }
+
+    pub fn is_quiet(&self, time: NaiveTime) -> bool {
+        match self.quiet_hours {
+            Some((start, end)) if start <= end => time >= start && time < end,
+            // Quiet hours spanning midnight, e.g. 22:00-07:00
+            Some((start, end)) => time >= start || time < end,
+            None => false,
+        }
+    }
+
+    pub fn next_change(&self, now: DateTime<Local>) -> DateTime<Local> {
+        let next = match self.refresh_time {
+            Some(refresh_time) => {
+                let today = now.date_naive().and_time(refresh_time);
+                if today > now.naive_local() {
+                    to_local(today)
+                } else {
+                    to_local(today + TimeDelta::days(1))
+                }
+            }
+            None => now + self.interval,
+        };
+
+        match self.quiet_hours {
+            Some((_, end)) if self.is_quiet(next.time()) => {
+                let end_today = next.date_naive().and_time(end);
+                if end_today > next.naive_local() {
+                    to_local(end_today)
+                } else {
+                    to_local(end_today + TimeDelta::days(1))
+                }
+            }
+            _ => next,
+        }
+    }
+}
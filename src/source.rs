@@ -0,0 +1,206 @@
+use crate::apod::ApodClientBuilder;
+use crate::{Error, Result};
+use chrono::{Local, NaiveDate};
+use reqwest::Client;
+use serde::Deserialize;
+use std::future::Future;
+use std::pin::Pin;
+
+pub type FetchFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<FetchedImage>>> + Send + 'a>>;
+pub type ExplanationFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<String>>> + Send + 'a>>;
+
+#[derive(Debug, Clone)]
+pub struct ImageMetadata {
+    pub date: NaiveDate,
+    pub title: String,
+    pub explanation: String,
+    pub copyright: Option<String>,
+}
+
+// The image itself is streamed to disk by the caller
+#[derive(Debug, Clone)]
+pub struct FetchedImage {
+    pub url: String,
+    pub metadata: ImageMetadata,
+}
+
+pub trait ImageSource: Send + Sync {
+    fn name(&self) -> &str;
+    fn fetch(&self, date: NaiveDate) -> FetchFuture<'_>;
+}
+
+// Provides translated explanations, None falls back to the English text from the API
+pub trait ExplanationProvider: Send + Sync {
+    fn language(&self) -> &str;
+    fn fetch(&self, date: NaiveDate) -> ExplanationFuture<'_>;
+}
+
+pub fn explanation_provider(
+    language: &str,
+    mirror: Option<&str>,
+) -> Option<Box<dyn ExplanationProvider>> {
+    if language.is_empty() || language.eq_ignore_ascii_case("en") {
+        return None;
+    }
+    match mirror {
+        Some(url_template) => Some(Box::new(MirrorExplanations::new(language, url_template))),
+        None => {
+            eprintln!(
+                "Warning: No explanation_mirror configured for language '{}', using English",
+                language
+            );
+            None
+        }
+    }
+}
+
+// The APOD source is built from the caller's client so it keeps the api key and base URL
+pub fn source_from_name(name: &str, apod: &ApodClientBuilder) -> Option<Box<dyn ImageSource>> {
+    match name.to_lowercase().as_str() {
+        "bing" => Some(Box::new(BingSource::new())),
+        "apod" => Some(Box::new(apod.clone().build())),
+        _ => None,
+    }
+}
+
+pub fn sources_from_names(names: &[String], apod: &ApodClientBuilder) -> Vec<Box<dyn ImageSource>> {
+    names
+        .iter()
+        .filter_map(|name| {
+            let source = source_from_name(name, apod);
+            if source.is_none() {
+                eprintln!("Warning: Unknown image source '{}', ignoring", name);
+            }
+            source
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct MirrorResponse {
+    explanation: String,
+}
+
+// Any mirror serving APOD shaped JSON, {language} and {date} (YYYY-MM-DD) in the URL are replaced
+pub struct MirrorExplanations {
+    client: Client,
+    language: String,
+    url_template: String,
+}
+
+impl MirrorExplanations {
+    pub fn new(language: &str, url_template: &str) -> Self {
+        Self {
+            client: Client::new(),
+            language: language.to_string(),
+            url_template: url_template.to_string(),
+        }
+    }
+}
+
+impl ExplanationProvider for MirrorExplanations {
+    fn language(&self) -> &str {
+        &self.language
+    }
+
+    fn fetch(&self, date: NaiveDate) -> ExplanationFuture<'_> {
+        Box::pin(async move {
+            let url = self
+                .url_template
+                .replace("{language}", &self.language)
+                .replace("{date}", &date.format("%Y-%m-%d").to_string());
+            let response = self.client.get(&url).send().await?;
+            // Translations usually lag behind, a missing day is not an error
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            if !response.status().is_success() {
+                return Err(Error::Api(format!(
+                    "Failed to fetch translated explanation: HTTP {}",
+                    response.status()
+                )));
+            }
+
+            let data: MirrorResponse = response.json().await?;
+            let explanation = data.explanation.trim();
+            Ok((!explanation.is_empty()).then(|| explanation.to_string()))
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BingResponse {
+    images: Vec<BingImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BingImage {
+    url: String,
+    title: String,
+    copyright: Option<String>,
+}
+
+pub struct BingSource {
+    client: Client,
+    market: String,
+}
+
+impl Default for BingSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BingSource {
+    // Bing only keeps the last few days of its archive available
+    const MAX_DAYS_BACK: i64 = 7;
+
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+            market: "en-US".to_string(),
+        }
+    }
+}
+
+impl ImageSource for BingSource {
+    fn name(&self) -> &str {
+        "bing"
+    }
+
+    fn fetch(&self, date: NaiveDate) -> FetchFuture<'_> {
+        Box::pin(async move {
+            let days_back = (Local::now().date_naive() - date).num_days();
+            if !(0..=Self::MAX_DAYS_BACK).contains(&days_back) {
+                return Ok(None);
+            }
+
+            let url = format!(
+                "https://www.bing.com/HPImageArchive.aspx?format=js&idx={}&n=1&mkt={}",
+                days_back, self.market
+            );
+            let response = self.client.get(&url).send().await?;
+            if !response.status().is_success() {
+                return Err(Error::Api(format!(
+                    "Failed to fetch Bing data: HTTP {}",
+                    response.status()
+                )));
+            }
+
+            let data: BingResponse = response.json().await?;
+            let Some(image) = data.images.into_iter().next() else {
+                return Ok(None);
+            };
+
+            Ok(Some(FetchedImage {
+                url: format!("https://www.bing.com{}", image.url),
+                metadata: ImageMetadata {
+                    date,
+                    explanation: image.copyright.clone().unwrap_or_default(),
+                    title: image.title,
+                    copyright: image.copyright,
+                },
+            }))
+        })
+    }
+}
@@ -1,4 +1,4 @@
-use apod_wallpaper::{gui, WallpaperConfig};
+use apod_wallpaper::{WallpaperConfig, config::ThumbnailFormat, gui};
 use clap::Parser;
 use std::fs::create_dir;
 
@@ -15,17 +15,32 @@ struct Args {
         help = "Folder to save wallpapers to (will be saved in config if used once)"
     )]
     folder: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        conflicts_with = "folder",
+        help = "Use one of the folders configured in folders by its name"
+    )]
+    folder_name: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        help = "Thumbnail format, jpeg keeps the cache small for PNG heavy folders"
+    )]
+    format: Option<ThumbnailFormat>,
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
     let config = WallpaperConfig::load_or_default()?;
-    let save_folder = args.folder.unwrap_or(config.save_folder);
+    let save_folder = match &args.folder_name {
+        Some(name) => config.folder_by_name(name)?,
+        None => args.folder.unwrap_or(config.save_folder),
+    };
 
     if !save_folder.exists() {
         create_dir(&save_folder)?;
     }
 
-    gui::run_switcher(save_folder)?;
+    gui::run_switcher(save_folder, args.format)?;
     Ok(())
 }
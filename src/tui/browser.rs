@@ -0,0 +1,154 @@
+use crate::Result;
+use crate::WallpaperConfig;
+use crate::desktop::get_wallpaper_manager_with;
+use crate::utils::{after_wallpaper_set, get_all_metadata, run_color_backends};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+};
+use std::path::{Path, PathBuf};
+
+struct ImageEntry {
+    path: PathBuf,
+    label: String,
+}
+
+struct ImageBrowser {
+    images: Vec<ImageEntry>,
+    list_state: ListState,
+    visible_rows: usize,
+    config: WallpaperConfig,
+    status: String,
+}
+
+impl ImageBrowser {
+    fn new(image_paths: Vec<PathBuf>) -> Self {
+        let config = WallpaperConfig::load_or_default().unwrap_or_default();
+
+        let (mut metadata, status) = match get_all_metadata(&image_paths) {
+            Ok(metadata) => (metadata, String::new()),
+            Err(e) => (Default::default(), format!("Titles unavailable: {}", e)),
+        };
+
+        let images = image_paths
+            .into_iter()
+            .rev()
+            .map(|path| {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                let label = match metadata.remove(&path).and_then(|mut m| m.remove("Title")) {
+                    Some(serde_json::Value::String(title)) => format!("{}  {}", file_name, title),
+                    _ => file_name.to_string(),
+                };
+                ImageEntry { path, label }
+            })
+            .collect::<Vec<_>>();
+
+        let mut list_state = ListState::default();
+        if !images.is_empty() {
+            list_state.select(Some(0));
+        }
+
+        Self {
+            images,
+            list_state,
+            visible_rows: 1,
+            config,
+            status,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            let total = self.images.len();
+            let selected = self.list_state.selected().unwrap_or(0);
+            let page_size = self.visible_rows.max(1);
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ if total == 0 => {}
+                KeyCode::Down | KeyCode::Char('j') => {
+                    self.list_state.select(Some((selected + 1).min(total - 1)))
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.list_state.select(Some(selected.saturating_sub(1)))
+                }
+                KeyCode::Home | KeyCode::Char('g') => self.list_state.select(Some(0)),
+                KeyCode::End | KeyCode::Char('G') => self.list_state.select(Some(total - 1)),
+                KeyCode::PageDown => self
+                    .list_state
+                    .select(Some((selected + page_size).min(total - 1))),
+                KeyCode::PageUp => self
+                    .list_state
+                    .select(Some(selected.saturating_sub(page_size))),
+                KeyCode::Enter => {
+                    let path = self.images[selected].path.clone();
+                    self.status = match self.set_wallpaper(&path) {
+                        Ok(()) => format!("Wallpaper set to {}", path.display()),
+                        Err(e) => format!("Failed to set wallpaper: {}", e),
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> Result<()> {
+        let manager = get_wallpaper_manager_with(self.config.desktop_options())?;
+        manager.set_wallpaper(path, None)?;
+        manager.persist_wallpaper(path, None)?;
+        after_wallpaper_set(path, None, &self.config)?;
+        run_color_backends(path, &self.config.color_backends)?;
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [list_area, status_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        // Two rows are taken up by the block border
+        self.visible_rows = list_area.height.saturating_sub(2) as usize;
+
+        let items: Vec<ListItem> = self
+            .images
+            .iter()
+            .map(|image| ListItem::new(image.label.as_str()))
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::bordered().title(" APOD Wallpapers "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let status = if self.images.is_empty() {
+            "No images found. Run apod-wallpaper download first. (q to quit)"
+        } else if self.status.is_empty() {
+            "Up/Down to move, Enter to set wallpaper, q to quit"
+        } else {
+            self.status.as_str()
+        };
+        frame.render_widget(Paragraph::new(Line::from(status)), status_area);
+    }
+}
+
+pub fn run_image_browser(image_paths: Vec<PathBuf>) -> Result<()> {
+    let mut browser = ImageBrowser::new(image_paths);
+
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+
+    result
+}
@@ -0,0 +1,11 @@
+pub mod browser;
+
+use std::path::PathBuf;
+
+pub fn run_browser(save_folder: PathBuf) -> crate::Result<()> {
+    let images = tokio::runtime::Runtime::new()?
+        .block_on(crate::utils::get_image_files(&save_folder))
+        .map_err(|e| crate::Error::Config(format!("Could not load images: {e}")))?;
+
+    browser::run_image_browser(images)
+}
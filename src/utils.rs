@@ -1,9 +1,30 @@
-use crate::{Error, Result};
+use crate::Result;
 use std::fs::{create_dir, write};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::process::Command;
 
-#[cfg(feature = "gui")]
+#[cfg(any(
+    feature = "download-only",
+    feature = "gui",
+    feature = "applet",
+    feature = "tui"
+))]
+use crate::Error;
+#[cfg(any(
+    feature = "download-only",
+    feature = "gui",
+    feature = "applet",
+    feature = "tui"
+))]
+use std::path::Path;
+
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
+use crate::config::ColorBackend;
+#[cfg(any(feature = "cli", feature = "gui"))]
+use crate::config::NotificationBody;
+#[cfg(any(feature = "download-only", feature = "gui"))]
+use crate::config::ThumbnailFormat;
+#[cfg(any(feature = "gui", feature = "tui"))]
 use tokio::fs;
 
 const NASA_SVG: &[u8] = include_bytes!("../assets/nasa.svg");
@@ -25,24 +46,58 @@ pub fn get_nasa_svg_path() -> Result<PathBuf> {
     location.push("nasa.svg");
 
     if !location.exists() {
-        write(&location.as_path(), NASA_SVG)?;
+        write(location.as_path(), NASA_SVG)?;
     }
     Ok(location)
 }
 
+// Minimal containers may have no cache directory at all, the temp directory is the last resort
 pub fn get_cache_dir() -> Result<PathBuf> {
     let cache_dir = dirs::cache_dir()
-        .map(|dir| dir.join(PathBuf::from("apodwallpaper")))
-        .ok_or_else(|| Error::DesktopEnv("Could not find cache directory".to_string()));
-    if let Ok(ref dir) = cache_dir {
-        if !dir.exists() {
-            create_dir(dir)?;
+        .or_else(|| std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from))
+        .or_else(|| {
+            std::env::var("HOME")
+                .ok()
+                .map(|home| PathBuf::from(home).join(".cache"))
+        })
+        .unwrap_or_else(std::env::temp_dir)
+        .join(PathBuf::from("apodwallpaper"));
+    if !cache_dir.exists() {
+        std::fs::create_dir_all(&cache_dir)?;
+    }
+    Ok(cache_dir)
+}
+
+#[cfg(any(feature = "download-only", feature = "gui", feature = "tui"))]
+pub struct FileLock {
+    _file: std::fs::File,
+}
+
+#[cfg(any(feature = "download-only", feature = "gui", feature = "tui"))]
+pub fn acquire_lock(name: &str) -> Result<FileLock> {
+    let lock_path = get_cache_dir()?.join(format!("{}.lock", name));
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(std::fs::TryLockError::WouldBlock) => {
+            eprintln!(
+                "Another apod-wallpaper process holds the {} lock, waiting...",
+                name
+            );
+            file.lock()?;
         }
+        Err(std::fs::TryLockError::Error(e)) => return Err(e.into()),
     }
-    cache_dir
+
+    Ok(FileLock { _file: file })
 }
 
-#[cfg(any(feature = "cli", feature = "gui"))]
+#[cfg(any(feature = "download-only", feature = "gui", feature = "tui"))]
 pub fn get_config_dir() -> Result<PathBuf> {
     let config_dir = dirs::config_dir()
         .or_else(|| {
@@ -62,7 +117,308 @@ pub fn get_config_dir() -> Result<PathBuf> {
     Ok(config_dir)
 }
 
+#[cfg(feature = "download-only")]
+pub fn create_save_folder(folder: &Path) -> Result<()> {
+    if !folder.exists() {
+        std::fs::create_dir_all(folder).map_err(|e| {
+            Error::Config(format!(
+                "Could not create save folder {}: {}",
+                folder.display(),
+                e
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "download-only")]
+pub fn check_save_folder_writable(folder: &Path) -> Result<()> {
+    let hint = "Use --folder to choose a different location.";
+    if !folder.exists() {
+        std::fs::create_dir_all(folder).map_err(|e| {
+            Error::Config(format!(
+                "Could not create save folder {}: {}. {}",
+                folder.display(),
+                e,
+                hint
+            ))
+        })?;
+    }
+
+    let probe = folder.join(".apod-wallpaper-write-test");
+    write(&probe, b"").map_err(|e| {
+        Error::Config(format!(
+            "Save folder {} is not writable: {}. {}",
+            folder.display(),
+            e,
+            hint
+        ))
+    })?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+#[cfg(feature = "download-only")]
+pub fn collect_files(directory: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = std::fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_dir() {
+                directories.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    files
+}
+
+// Only the formats wallpapers are set from, kept originals like GIFs are left out
+#[cfg(feature = "download-only")]
+pub fn is_image_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| matches!(ext.as_str(), "jpg" | "jpeg" | "png"))
+}
+
+#[cfg(feature = "download-only")]
+pub fn list_image_files(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut images = Vec::new();
+
+    if !directory.exists() {
+        return Ok(images);
+    }
+
+    for path in collect_files(directory) {
+        if is_image_file(&path) {
+            images.push(path);
+        }
+    }
+
+    images.sort();
+    Ok(images)
+}
+
+#[cfg(any(feature = "download-only", feature = "gui"))]
+pub fn thumbnail_path(image_path: &Path, format: ThumbnailFormat) -> Result<PathBuf> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    image_path.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    let original_name = image_path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = match format {
+        ThumbnailFormat::Source => image_path.extension().unwrap_or_default().to_string_lossy(),
+        ThumbnailFormat::Jpeg => "jpg".into(),
+    };
+    Ok(get_cache_dir()?
+        .join("thumbnails")
+        .join(format!("thumb_{}_{}.{}", original_name, hash, extension)))
+}
+
+#[cfg(feature = "download-only")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrunePolicy {
+    pub max_size_mb: Option<u64>,
+    pub max_count: Option<usize>,
+}
+
+// Also removes the cached thumbnails of the image
+#[cfg(feature = "download-only")]
+pub fn remove_image(image_path: &Path) -> Result<()> {
+    std::fs::remove_file(image_path)?;
+    for format in [ThumbnailFormat::Source, ThumbnailFormat::Jpeg] {
+        if let Ok(thumbnail) = thumbnail_path(image_path, format) {
+            let _ = std::fs::remove_file(thumbnail_color_path(&thumbnail));
+            let _ = std::fs::remove_file(thumbnail);
+        }
+    }
+    Ok(())
+}
+
+// Deletes the oldest images until the folder fits the policy, returns the removed files
+#[cfg(feature = "download-only")]
+pub fn prune_archive(folder: &Path, policy: PrunePolicy, keep: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    if policy.max_size_mb.is_none() && policy.max_count.is_none() {
+        return Ok(Vec::new());
+    }
+
+    let keep: Vec<PathBuf> = keep
+        .iter()
+        .map(|path| path.canonicalize().unwrap_or(path.clone()))
+        .collect();
+    let mut images: Vec<(PathBuf, u64)> = list_image_files(folder)?
+        .into_iter()
+        .map(|path| {
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            (path, size)
+        })
+        .collect();
+    // Images without a date in their name count as oldest
+    images.sort_by_key(|(path, _)| (date_from_file_name(path), path.clone()));
+
+    let max_size = policy.max_size_mb.map(|mb| mb * 1_000_000);
+    let mut count = images.len();
+    let mut size: u64 = images.iter().map(|(_, size)| size).sum();
+    let mut removed = Vec::new();
+    for (path, file_size) in images {
+        let over_count = policy.max_count.is_some_and(|max| count > max);
+        let over_size = max_size.is_some_and(|max| size > max);
+        if !over_count && !over_size {
+            break;
+        }
+        if keep.contains(&path.canonicalize().unwrap_or(path.clone())) {
+            continue;
+        }
+
+        remove_image(&path)?;
+        count -= 1;
+        size = size.saturating_sub(file_size);
+        removed.push(path);
+    }
+
+    Ok(removed)
+}
+
 #[cfg(any(feature = "cli", feature = "gui"))]
+pub fn assign_images_to_screens<'a>(
+    screens: &'a [crate::desktop::Screen],
+    images: &'a [PathBuf],
+) -> Vec<(&'a crate::desktop::Screen, &'a PathBuf)> {
+    let mut remaining: Vec<(&PathBuf, Option<f64>)> = images
+        .iter()
+        .map(|path| {
+            let ratio = image::image_dimensions(path)
+                .ok()
+                .filter(|(width, height)| *width > 0 && *height > 0)
+                .map(|(width, height)| width as f64 / height as f64);
+            (path, ratio)
+        })
+        .collect();
+
+    let mut assignments = Vec::new();
+    for screen in screens {
+        if remaining.is_empty() {
+            break;
+        }
+
+        let index = match screen.aspect_ratio() {
+            Some(screen_ratio) => remaining
+                .iter()
+                .enumerate()
+                .min_by(|(_, (_, a)), (_, (_, b))| {
+                    let distance = |ratio: &Option<f64>| {
+                        ratio.map_or(f64::MAX, |r| (r / screen_ratio).ln().abs())
+                    };
+                    distance(a).total_cmp(&distance(b))
+                })
+                .map(|(index, _)| index)
+                .unwrap_or(0),
+            None => 0,
+        };
+
+        assignments.push((screen, remaining.remove(index).0));
+    }
+
+    assignments
+}
+
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
+pub fn update_lockscreen_image(image_path: &Path, lockscreen_path: &Path) -> Result<()> {
+    if let Some(parent) = lockscreen_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Copy next to the target first so the lock screen never reads a half-written file
+    let mut temp_name = lockscreen_path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+    std::fs::copy(image_path, &temp_path)?;
+    std::fs::rename(&temp_path, lockscreen_path).map_err(|e| {
+        Error::Config(format!(
+            "Could not update lock screen image {}: {}",
+            lockscreen_path.display(),
+            e
+        ))
+    })?;
+    Ok(())
+}
+
+// Bookkeeping after any wallpaper change, a failed history write only warns
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
+pub fn after_wallpaper_set(
+    image_path: &Path,
+    screen: Option<&str>,
+    config: &crate::WallpaperConfig,
+) -> Result<()> {
+    after_wallpaper_change(image_path, screen, false, config)
+}
+
+// Same as after_wallpaper_set, but history treats it as a step back
+#[cfg(any(feature = "cli", feature = "gui"))]
+pub fn after_wallpaper_restored(
+    image_path: &Path,
+    screen: Option<&str>,
+    config: &crate::WallpaperConfig,
+) -> Result<()> {
+    after_wallpaper_change(image_path, screen, true, config)
+}
+
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
+fn after_wallpaper_change(
+    image_path: &Path,
+    screen: Option<&str>,
+    restored: bool,
+    config: &crate::WallpaperConfig,
+) -> Result<()> {
+    if let Err(e) = crate::history::record_wallpaper(image_path, screen, restored) {
+        eprintln!("Warning: Failed to record wallpaper history: {}", e);
+    }
+    if let Some(lockscreen_path) = &config.lockscreen_path {
+        update_lockscreen_image(image_path, lockscreen_path)?;
+    }
+    if let Some(latest_symlink) = &config.latest_symlink {
+        update_latest_symlink(image_path, latest_symlink)?;
+    }
+    Ok(())
+}
+
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
+pub fn update_latest_symlink(image_path: &Path, link_path: &Path) -> Result<()> {
+    if let Some(parent) = link_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // The link is replaced by a rename so readers always find a valid target
+    let mut temp_name = link_path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+    let _ = std::fs::remove_file(&temp_path);
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(image_path.canonicalize()?, &temp_path)?;
+    // Symlinks need extra privileges elsewhere, so the image is copied instead
+    #[cfg(not(unix))]
+    std::fs::copy(image_path, &temp_path)?;
+    std::fs::rename(&temp_path, link_path).map_err(|e| {
+        Error::Config(format!(
+            "Could not update latest wallpaper link {}: {}",
+            link_path.display(),
+            e
+        ))
+    })?;
+    Ok(())
+}
+
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
 pub fn generate_pywal_colors(image_path: &Path) -> Result<()> {
     if !command_exists("wal") {
         return Err(Error::DesktopEnv(
@@ -84,7 +440,7 @@ pub fn generate_pywal_colors(image_path: &Path) -> Result<()> {
     Ok(())
 }
 
-#[cfg(any(feature = "cli", feature = "gui"))]
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
 pub fn generate_wallust_colors(image_path: &Path) -> Result<()> {
     if !command_exists("wallust") {
         return Err(Error::DesktopEnv("wallust not found in PATH".to_string()));
@@ -104,7 +460,71 @@ pub fn generate_wallust_colors(image_path: &Path) -> Result<()> {
     Ok(())
 }
 
-#[cfg(feature = "gui")]
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
+pub fn generate_matugen_colors(image_path: &Path) -> Result<()> {
+    if !command_exists("matugen") {
+        return Err(Error::DesktopEnv("matugen not found in PATH".to_string()));
+    }
+
+    let output = Command::new("matugen")
+        .args(["image", &image_path.to_string_lossy()])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(Error::DesktopEnv(format!(
+            "Failed to generate matugen colors: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
+pub fn generate_colors(image_path: &Path, backend: ColorBackend) -> Result<()> {
+    match backend {
+        ColorBackend::Pywal => generate_pywal_colors(image_path),
+        ColorBackend::Wallust => generate_wallust_colors(image_path),
+        ColorBackend::Matugen => generate_matugen_colors(image_path),
+    }
+}
+
+// Backends that are not installed are skipped, so theming still happens with whatever is present
+#[cfg(any(feature = "cli", feature = "gui", feature = "tui"))]
+pub fn run_color_backends(
+    image_path: &Path,
+    backends: &[ColorBackend],
+) -> Result<Vec<ColorBackend>> {
+    let mut generated = Vec::new();
+    let mut last_error = None;
+    for &backend in backends {
+        if !command_exists(backend.command()) {
+            continue;
+        }
+        match generate_colors(image_path, backend) {
+            Ok(()) => generated.push(backend),
+            Err(e) => {
+                eprintln!("Warning: {}", e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    match last_error {
+        Some(e) if generated.is_empty() => Err(e),
+        None if generated.is_empty() && !backends.is_empty() => Err(Error::DesktopEnv(format!(
+            "None of the color backends are installed: {}",
+            backends
+                .iter()
+                .map(|backend| backend.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+        _ => Ok(generated),
+    }
+}
+
+#[cfg(any(feature = "gui", feature = "tui"))]
 pub async fn get_image_files(
     directory: &Path,
 ) -> std::result::Result<Vec<PathBuf>, Box<dyn std::error::Error + Send + Sync>> {
@@ -115,15 +535,20 @@ pub async fn get_image_files(
         return Ok(images);
     }
 
-    let mut entries = fs::read_dir(directory).await?;
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let mut entries = fs::read_dir(&directory).await?;
 
-    while let Some(entry) = entries.next_entry().await? {
-        let path = entry.path();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
 
-        if let Some(extension) = path.extension() {
-            let ext_str = extension.to_string_lossy().to_lowercase();
-            if matches!(ext_str.as_str(), "jpg" | "jpeg" | "png") {
-                images.push(path);
+            if entry.file_type().await?.is_dir() {
+                directories.push(path);
+            } else if let Some(extension) = path.extension() {
+                let ext_str = extension.to_string_lossy().to_lowercase();
+                if matches!(ext_str.as_str(), "jpg" | "jpeg" | "png") {
+                    images.push(path);
+                }
             }
         }
     }
@@ -132,43 +557,246 @@ pub async fn get_image_files(
     Ok(images)
 }
 
-#[cfg(feature = "gui")]
-pub fn generate_thumbnail(image_path: &Path, thumbnail_path: &Path, size: u32) -> Result<()> {
+// Longest side of the thumbnails shown in the switcher grid
+#[cfg(any(feature = "download-only", feature = "gui"))]
+pub const THUMBNAIL_SIZE: u32 = 400;
+
+#[cfg(any(feature = "download-only", feature = "gui"))]
+pub fn generate_thumbnail(
+    image_path: &Path,
+    thumbnail_path: &Path,
+    size: u32,
+    format: ThumbnailFormat,
+    quality: u8,
+) -> Result<()> {
     let img = image::open(image_path)?;
+    save_thumbnail(&img, thumbnail_path, size, format, quality)
+}
+
+// Creates the given (image, thumbnail) pairs on at most threads threads, on_done runs after each one
+#[cfg(feature = "download-only")]
+pub fn generate_thumbnails(
+    thumbnails: &[(PathBuf, PathBuf)],
+    format: ThumbnailFormat,
+    quality: u8,
+    threads: usize,
+    on_done: impl Fn(&Path, Result<()>) + Sync,
+) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let next = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, thumbnails.len().max(1)) {
+            scope.spawn(|| {
+                while let Some((image, thumbnail)) =
+                    thumbnails.get(next.fetch_add(1, Ordering::Relaxed))
+                {
+                    let result =
+                        generate_thumbnail(image, thumbnail, THUMBNAIL_SIZE, format, quality);
+                    on_done(image, result);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(any(feature = "download-only", feature = "gui"))]
+pub fn save_thumbnail(
+    img: &image::DynamicImage,
+    thumbnail_path: &Path,
+    size: u32,
+    format: ThumbnailFormat,
+    quality: u8,
+) -> Result<()> {
     let thumbnail = img.thumbnail(size, size);
 
     if let Some(parent) = thumbnail_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
-    thumbnail.save(thumbnail_path)?;
+    match format {
+        ThumbnailFormat::Source => thumbnail.save(thumbnail_path)?,
+        ThumbnailFormat::Jpeg => {
+            let file = std::io::BufWriter::new(std::fs::File::create(thumbnail_path)?);
+            let encoder =
+                image::codecs::jpeg::JpegEncoder::new_with_quality(file, quality.clamp(1, 100));
+            thumbnail.to_rgb8().write_with_encoder(encoder)?;
+        }
+    }
+    write_thumbnail_color(thumbnail_path, average_color(&thumbnail))
+}
+
+// The placeholder color is kept next to the thumbnail as "rrggbb"
+#[cfg(any(feature = "download-only", feature = "gui"))]
+fn thumbnail_color_path(thumbnail_path: &Path) -> PathBuf {
+    thumbnail_path.with_extension("color")
+}
+
+#[cfg(any(feature = "download-only", feature = "gui"))]
+fn write_thumbnail_color(thumbnail_path: &Path, [r, g, b]: [u8; 3]) -> Result<()> {
+    write(
+        thumbnail_color_path(thumbnail_path),
+        format!("{:02x}{:02x}{:02x}", r, g, b),
+    )?;
     Ok(())
 }
 
+// Thumbnails saved before colors were stored get theirs computed once
+#[cfg(feature = "gui")]
+pub fn thumbnail_color(thumbnail_path: &Path) -> Option<[u8; 3]> {
+    let stored = std::fs::read_to_string(thumbnail_color_path(thumbnail_path)).ok();
+    let parsed = stored.as_deref().map(str::trim).and_then(|hex| {
+        let value = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)?;
+        Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+    });
+    if parsed.is_some() {
+        return parsed;
+    }
+
+    let color = dominant_color(thumbnail_path).ok()?;
+    if let Err(e) = write_thumbnail_color(thumbnail_path, color) {
+        println!("Failed to store thumbnail color: {}", e);
+    }
+    Some(color)
+}
+
+#[cfg(any(feature = "download-only", feature = "gui"))]
+pub fn dominant_color(image_path: &Path) -> Result<[u8; 3]> {
+    Ok(average_color(&image::open(image_path)?))
+}
+
+#[cfg(any(feature = "download-only", feature = "gui"))]
+pub fn average_color(img: &image::DynamicImage) -> [u8; 3] {
+    let small = img.thumbnail(16, 16).to_rgb8();
+    let pixel_count = (small.width() * small.height()).max(1) as u64;
+
+    let mut sum = [0u64; 3];
+    for pixel in small.pixels() {
+        for (channel, value) in sum.iter_mut().zip(pixel.0) {
+            *channel += value as u64;
+        }
+    }
+
+    sum.map(|channel| (channel / pixel_count) as u8)
+}
+
+#[cfg(any(feature = "download-only", feature = "gui", feature = "tui"))]
+pub fn image_date(image_path: &Path) -> Option<chrono::NaiveDate> {
+    date_from_file_name(image_path).or_else(|| {
+        let mut metadata = get_all_metadata(&[image_path.to_path_buf()]).ok()?;
+        date_from_metadata(&metadata.remove(image_path)?)
+    })
+}
+
+#[cfg(any(feature = "download-only", feature = "gui", feature = "tui"))]
+pub fn date_from_file_name(image_path: &Path) -> Option<chrono::NaiveDate> {
+    let file_name = image_path.file_name()?.to_string_lossy();
+    chrono::NaiveDate::parse_from_str(file_name.get(..10)?, "%Y-%m-%d").ok()
+}
+
+#[cfg(any(feature = "download-only", feature = "gui", feature = "tui"))]
+pub fn date_from_metadata(
+    metadata: &serde_json::Map<String, serde_json::Value>,
+) -> Option<chrono::NaiveDate> {
+    ["DateTimeOriginal", "CreateDate", "Date"]
+        .iter()
+        .filter_map(|key| metadata.get(*key)?.as_str())
+        .find_map(|value| {
+            // exiftool prints dates as "YYYY:MM:DD HH:MM:SS"
+            let date = value.get(..10)?;
+            chrono::NaiveDate::parse_from_str(date, "%Y:%m:%d")
+                .or_else(|_| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+                .ok()
+        })
+}
+
+#[cfg(any(feature = "cli", feature = "gui"))]
+pub fn apod_page_url(image_path: &Path) -> Option<String> {
+    let stem = image_path.file_stem()?.to_string_lossy();
+    // Images from fallback sources carry the source name after the date
+    if date_from_file_name(image_path).is_some() && !matches!(stem.get(10..), Some("" | "-sd")) {
+        return None;
+    }
+    let date = image_date(image_path)?;
+    Some(format!(
+        "https://apod.nasa.gov/apod/ap{}.html",
+        date.format("%y%m%d")
+    ))
+}
+
+// Resolves on SIGTERM, as sent by systemd when stopping the service, or on Ctrl+C
+#[cfg(any(feature = "cli", feature = "applet"))]
+pub async fn shutdown_signal() -> Result<()> {
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    tokio::select! {
+        _ = terminate.recv() => Ok(()),
+        result = tokio::signal::ctrl_c() => Ok(result?),
+    }
+}
+
+// Falls back to the given message when the image has no metadata
+#[cfg(any(feature = "cli", feature = "gui"))]
+pub fn notification_body(mode: NotificationBody, image_path: &Path, fallback: &str) -> String {
+    let mut metadata = get_all_metadata(&[image_path.to_path_buf()]).unwrap_or_default();
+    let Some(metadata) = metadata.remove(image_path) else {
+        return fallback.to_string();
+    };
+    let field = |key: &str| metadata.get(key).and_then(|value| value.as_str());
+    match field("Title") {
+        Some(title) => mode.format(title, field("Description").unwrap_or_default()),
+        None => fallback.to_string(),
+    }
+}
+
 #[cfg(any(feature = "cli", feature = "gui"))]
-pub fn send_notification(title: &str, message: &str, image: Option<&Path>) -> Result<()> {
+pub fn send_notification(
+    title: &str,
+    message: &str,
+    image: Option<&Path>,
+    link: Option<&str>,
+) -> Result<()> {
     let mut notification = notify_rust::Notification::new();
     notification.summary(title).body(message);
 
     if let Some(image_path) = image {
         notification.image_path(image_path.to_string_lossy().as_ref());
     }
+    if link.is_some() {
+        notification.action("default", "Read more");
+    }
 
-    notification
+    let handle = notification
         .show()
         .map_err(|e| Error::DesktopEnv(e.to_string()))?;
+
+    // Waiting for the click blocks until the notification is closed, which may be never,
+    // so it happens on a detached thread that ends with the process
+    if let Some(link) = link {
+        let link = link.to_string();
+        std::thread::spawn(move || {
+            handle.wait_for_action(|action| {
+                if action == "default"
+                    && let Err(e) = Command::new("xdg-open").arg(&link).spawn()
+                {
+                    eprintln!("Failed to open {}: {}", link, e);
+                }
+            });
+        });
+    }
     Ok(())
 }
 
 #[cfg(feature = "applet")]
-pub fn get_metadata_from_image(image_path: &PathBuf, key: &str) -> Option<String> {
+pub fn get_metadata_from_image(image_path: &Path, key: &str) -> Option<String> {
     if !command_exists("exiftool") {
         println!("Couldn't find exiftool (needed for metadata extraction)");
         return None;
     }
 
     let output = match Command::new("exiftool")
-        .args(&[
+        .args([
             "-s",
             "-s",
             "-s",
@@ -199,3 +827,50 @@ pub fn get_metadata_from_image(image_path: &PathBuf, key: &str) -> Option<String
         Some(result.to_string())
     }
 }
+
+#[cfg(any(
+    feature = "download-only",
+    feature = "gui",
+    feature = "applet",
+    feature = "tui"
+))]
+pub fn get_all_metadata(
+    image_paths: &[PathBuf],
+) -> Result<std::collections::HashMap<PathBuf, serde_json::Map<String, serde_json::Value>>> {
+    let mut metadata = std::collections::HashMap::new();
+    if image_paths.is_empty() {
+        return Ok(metadata);
+    }
+
+    if !command_exists("exiftool") {
+        return Err(Error::DesktopEnv(
+            "exiftool not found in PATH (needed for metadata extraction)".to_string(),
+        ));
+    }
+
+    for chunk in image_paths.chunks(200) {
+        let output = Command::new("exiftool")
+            .arg("-json")
+            .args(chunk.iter().map(|path| path.as_os_str()))
+            .output()?;
+
+        // exiftool exits non-zero if any single file fails, but still prints the rest
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim().is_empty() {
+            continue;
+        }
+
+        let entries: Vec<serde_json::Map<String, serde_json::Value>> =
+            serde_json::from_str(&stdout).map_err(|e| {
+                Error::DesktopEnv(format!("Failed to parse exiftool output: {}", e))
+            })?;
+
+        for mut entry in entries {
+            if let Some(serde_json::Value::String(source)) = entry.remove("SourceFile") {
+                metadata.insert(PathBuf::from(source), entry);
+            }
+        }
+    }
+
+    Ok(metadata)
+}